@@ -3,20 +3,27 @@
 mod ext;
 
 mod state;
-use state::{Config, Database, JsonWebToken};
+use state::{Config, Database, JsonWebToken, Mailer};
 
 mod rest;
 use rest::Rest;
 
+mod task;
+use task::Task;
+
 #[launch]
 async fn rocket() -> _ {
     let config = Config::load();
     let database = Database::from_config(&config);
     let jsonwebtoken = JsonWebToken::from_config(&config);
+    let mailer = Mailer::from_config(&config);
 
     rocket::build()
         .manage(config)
         .manage(database)
         .manage(jsonwebtoken)
+        .manage(mailer)
+        .attach(Database::index_fairing())
         .mount_rest()
+        .attach_tasks()
 }
\ No newline at end of file
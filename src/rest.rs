@@ -2,6 +2,12 @@ use rocket::{Build, Rocket};
 
 mod auth;
 
+mod account;
+
+mod admin;
+
+mod well_known;
+
 pub trait Rest {
     fn mount_rest(self) -> Self;
 }
@@ -9,5 +15,10 @@ pub trait Rest {
 impl Rest for Rocket<Build> {
     fn mount_rest(self) -> Self {
         self.mount(auth::MOUNT_POINT, auth::routes())
+            .mount(account::MOUNT_POINT, account::routes())
+            .mount(admin::MOUNT_POINT, admin::routes())
+            .mount(well_known::MOUNT_POINT, well_known::routes())
+            .attach(auth::onetime_password::fairing())
+            .attach(admin::bootstrap::fairing())
     }
 }
\ No newline at end of file
@@ -0,0 +1,54 @@
+use rocket::Route;
+
+mod public_key;
+
+mod access_token;
+
+mod registration;
+
+mod invitation;
+
+mod profile;
+
+mod deletion;
+
+pub(super) mod email;
+
+pub const MOUNT_POINT: &str = "/account";
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        // GET /account/keys
+        public_key::list,
+        // POST /account/keys
+        public_key::add,
+        // PATCH /account/keys/<id>
+        public_key::update,
+        // DELETE /account/keys/<id>
+        public_key::remove,
+        // GET /account/access-tokens
+        access_token::list,
+        // POST /account/access-tokens
+        access_token::create,
+        // DELETE /account/access-tokens/<id>
+        access_token::revoke,
+        // POST /account/register
+        registration::register,
+        // POST /account/register/confirmation
+        registration::confirm,
+        // POST /account/invitations
+        invitation::create,
+        // GET /account/me
+        profile::me,
+        // PATCH /account/me
+        profile::update,
+        // DELETE /account/me
+        deletion::request,
+        // DELETE /account/me/deletion
+        deletion::cancel,
+        // POST /account/me/email/verification
+        email::send_verification,
+        // POST /account/email/verification
+        email::verify,
+    ]
+}
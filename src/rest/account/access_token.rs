@@ -0,0 +1,265 @@
+#![allow(private_interfaces)]
+use chrono::Utc;
+use mongodb::{
+    bson,
+    bson::{doc, oid::ObjectId},
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    database::collection::{token::State, AccessToken},
+    scope,
+    scope::{AccountRead, AccountWrite},
+    secret,
+    DatabaseState,
+    RequireScope,
+};
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    name: String,
+    #[serde(default)]
+    scope: Option<Vec<String>>,
+    #[serde(default)]
+    expiry: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct AccessTokenResponse {
+    id: String,
+    name: String,
+    creation: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiry: Option<i64>,
+    state: State,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_seen: Option<i64>,
+}
+
+impl From<AccessToken> for AccessTokenResponse {
+    fn from(access_token: AccessToken) -> Self {
+        Self {
+            id: access_token.id.to_hex(),
+            name: access_token.name,
+            creation: access_token.id.timestamp().timestamp_millis(),
+            scope: access_token.scope,
+            expiry: access_token.expiry,
+            state: access_token.state,
+            last_seen: access_token.last_seen,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CreateResponse {
+    token: String,
+    #[serde(flatten)]
+    access_token: AccessTokenResponse,
+}
+
+/**
+ * Request:
+ * ```text
+ * GET /account/access-tokens HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * [
+ *     {
+ *         "id": "<ObjectId-Hex>",
+ *         "name": "<Name>",
+ *         "creation": <Timestamp-Milliseconds>,
+ *         "scope": ["<Scope>", ...],
+ *         "expiry": <Timestamp-Milliseconds>,
+ *         "state": "Normal" | { "Disabled": <Timestamp-Milliseconds> },
+ *         "last_seen": <Timestamp-Milliseconds>
+ *     },
+ *     ...
+ * ]
+ * ```
+ **/
+#[get("/access-tokens")]
+pub async fn list(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountRead>,
+) -> Result<Json<Vec<AccessTokenResponse>>, Status> {
+    let filter = doc! { "account": authorization.account.id };
+    let mut cursor = database.collections.access_token.find(filter)
+        .sort(doc! { "_id": -1 })
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let mut access_tokens = Vec::new();
+    while cursor.advance().await.map_err(|_| Status::InternalServerError)? {
+        let access_token = cursor.deserialize_current()
+            .map_err(|_| Status::InternalServerError)?;
+        access_tokens.push(AccessTokenResponse::from(access_token));
+    }
+    Ok(Json(access_tokens))
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /account/access-tokens HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "name": "<Name>",
+ *     "scope": ["<Scope>", ...],
+ *     "expiry": <Timestamp-Milliseconds>
+ * }
+ * ```
+ * Where the token grants full access if [scope] is not specified, and never expires if [expiry] is not specified.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 201 Created
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "token": "cloudy_pat_<Secret>",
+ *     <Access Token as in GET /account/access-tokens>
+ * }
+ * ```
+ *
+ * The token is returned in this response only, to be presented as `Authorization: Bearer cloudy_pat_<Secret>`.
+ * A scoped credential can only create tokens of its own scopes or narrower.
+ **/
+#[post("/access-tokens", data = "<json_request_body>")]
+pub async fn create(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<CreateRequest>,
+) -> Result<(Status, Json<CreateResponse>), Status> {
+    let create_request = json_request_body.into_inner();
+
+    let name = create_request.name.trim();
+    if name.is_empty() {
+        return Err(Status::BadRequest);
+    }
+    if create_request.scope.iter().flatten().any(|scope| !scope::is_known(scope)) {
+        return Err(Status::BadRequest);
+    }
+    if create_request.expiry.is_some_and(|expiry| expiry <= Utc::now().timestamp_millis()) {
+        return Err(Status::BadRequest);
+    }
+    if !is_within(authorization.scope(), create_request.scope.as_deref()) {
+        return Err(Status::Forbidden);
+    }
+
+    let secret = secret::generate(secret::BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+    let token = format!("{}{secret}", AccessToken::PREFIX);
+
+    let access_token = AccessToken::new(
+        authorization.account.id,
+        name.to_string(),
+        secret::hash(&token),
+        create_request.scope,
+        create_request.expiry,
+    );
+    database.collections.access_token.insert_one(&access_token)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok((Status::Created, Json(CreateResponse {
+        token,
+        access_token: AccessTokenResponse::from(access_token),
+    })))
+}
+
+/**
+ * Request:
+ * ```text
+ * DELETE /account/access-tokens/<ObjectId-Hex> HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * The token is disabled rather than deleted, remaining listed for auditing.
+ **/
+#[delete("/access-tokens/<id>")]
+pub async fn revoke(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    id: &str,
+) -> Result<Status, Status> {
+    let id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+    let state = bson::to_bson(&State::Disabled(Utc::now().timestamp_millis()))
+        .map_err(|_| Status::InternalServerError)?;
+    // Normal state is not serialized
+    let filter = doc! {
+        "_id": id,
+        "account": authorization.account.id,
+        "state": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "state": state },
+    };
+    let update_result = database.collections.access_token.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
+
+/**
+ * Whether the [requested] scopes of a new token are within the [granted] scopes of the creating credential,
+ * so that a token never grants more than its creator. Not specified scopes are full access.
+ **/
+fn is_within(granted: Option<&[String]>, requested: Option<&[String]>) -> bool {
+    match (granted, requested) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(granted), Some(requested)) => requested.iter().all(|scope| granted.contains(scope)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scope(scope: &[&str]) -> Vec<String> {
+        scope.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_within() {
+        let granted = scope(&["account:read", "account:write"]);
+
+        // Full access creates anything
+        assert!(is_within(None, None));
+        assert!(is_within(None, Some(&granted)));
+
+        // Scoped credentials create their own scopes or narrower
+        assert!(is_within(Some(&granted), Some(&granted)));
+        assert!(is_within(Some(&granted), Some(&scope(&["account:read"]))));
+        assert!(is_within(Some(&granted), Some(&[])));
+
+        // but neither full access nor other scopes
+        assert!(!is_within(Some(&granted), None));
+        assert!(!is_within(Some(&granted), Some(&scope(&["account:read", "admin"]))));
+        assert!(!is_within(Some(&scope(&["account:read"])), Some(&scope(&["account:write"]))));
+    }
+}
@@ -0,0 +1,168 @@
+#![allow(private_interfaces)]
+use chrono::{Duration, Utc};
+use mongodb::{bson, bson::doc};
+use rocket::{http::Status, serde::json::Json};
+use serde::Serialize;
+
+use crate::{
+    state::{
+        database::collection::token::State,
+        scope::AccountWrite,
+        Config,
+        ConfigState,
+        DatabaseState,
+        RequireScope,
+    },
+    str_vec,
+};
+
+#[derive(Serialize)]
+struct DeletionResponse {
+    deletion: i64,
+}
+
+/**
+ * Request the deletion of the authorized account, revoking all its sessions and personal access tokens.
+ * The account and all its data are purged after the configured grace period,
+ * during which logging in again and [cancel]ling the deletion remain possible.
+ *
+ * Request:
+ * ```text
+ * DELETE /account/me HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 202 Accepted
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "deletion": <Purge-Timestamp-Milliseconds>
+ * }
+ * ```
+ *
+ * Only sessions logged in recently can request the deletion, never personal access tokens.
+ * Responded with 403 Forbidden if the login is not recent,
+ * and 409 Conflict if the deletion has already been requested.
+ **/
+#[delete("/me")]
+pub async fn request(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+) -> Result<(Status, Json<DeletionResponse>), Status> {
+    if !authorization.is_recent_login() {
+        return Err(Status::Forbidden);
+    }
+    let account_id = authorization.account.id;
+    let now_timestamp = Utc::now();
+    let deletion = (now_timestamp + Duration::milliseconds(config.deletion_grace_period_millis())).timestamp_millis();
+
+    let filter = doc! {
+        "_id": account_id,
+        "deletion": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "deletion": deletion },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Conflict);
+    }
+
+    let state = bson::to_bson(&State::Disabled(now_timestamp.timestamp_millis()))
+        .map_err(|_| Status::InternalServerError)?;
+    // Normal state is not serialized
+    let filter = doc! {
+        "account": account_id,
+        "state": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "state": state },
+    };
+    database.collections.token.update_many(filter.clone(), update.clone())
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    database.collections.access_token.update_many(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok((Status::Accepted, Json(DeletionResponse { deletion })))
+}
+
+/**
+ * Cancel the requested deletion of the authorized account within the grace period.
+ * Revoked sessions and personal access tokens remain revoked.
+ *
+ * Request:
+ * ```text
+ * DELETE /account/me/deletion HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Responded with 404 Not Found if no deletion is requested.
+ **/
+#[delete("/me/deletion")]
+pub async fn cancel(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+) -> Result<Status, Status> {
+    let filter = doc! {
+        "_id": authorization.account.id,
+        "deletion": { "$gt": Utc::now().timestamp_millis() },
+    };
+    let update = doc! {
+        "$unset": { "deletion": "" },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
+
+// 30 days
+const DELETION_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60 * 1000;
+
+trait Deletion {
+    fn deletion_grace_period_millis(&self) -> i64;
+}
+
+impl Deletion for Config {
+    /**
+     * Delay in milliseconds between the deletion request and the purge of an account:
+     * "account.deletion.grace-period", set as [DELETION_GRACE_PERIOD] if not specified.
+     **/
+    fn deletion_grace_period_millis(&self) -> i64 {
+        self.get(str_vec!["account", "deletion", "grace-period"])
+            .and_then(|grace_period| grace_period.parse::<i64>().ok())
+            .filter(|grace_period| *grace_period >= 0)
+            .unwrap_or(DELETION_GRACE_PERIOD)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deletion_grace_period() {
+        assert_eq!(Config::of_pairs(&[]).deletion_grace_period_millis(), DELETION_GRACE_PERIOD);
+        let config = Config::of_pairs(&[("account.deletion.grace-period", "0")]);
+        assert_eq!(config.deletion_grace_period_millis(), 0);
+        let config = Config::of_pairs(&[("account.deletion.grace-period", "-1")]);
+        assert_eq!(config.deletion_grace_period_millis(), DELETION_GRACE_PERIOD);
+    }
+}
@@ -0,0 +1,281 @@
+#![allow(private_interfaces)]
+use chrono::{Duration, Utc};
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+use rocket::{http::Status, serde::json::Json};
+use serde::Deserialize;
+
+use crate::{
+    state::{
+        database::collection::{mail_token::Purpose, MailToken},
+        scope::AccountWrite,
+        secret,
+        Config,
+        ConfigState,
+        Database,
+        DatabaseState,
+        MailerState,
+        RequireScope,
+    },
+    str_vec,
+};
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+    token: String,
+}
+
+/**
+ * Send a verification link to the email address of the authorized account,
+ * invalidating any link sent before.
+ *
+ * Request:
+ * ```text
+ * POST /account/me/email/verification HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 202 Accepted
+ * ```
+ *
+ * The link is "account.email.verification-url" with the query "token=<Token>",
+ * for the client to submit the token to [verify].
+ * Responded with 409 Conflict if the account has no email address, or it has been verified,
+ * 429 Too Many Requests within "account.email.verification-cooldown" of the latest link,
+ * and 503 Service Unavailable if mail is not configured.
+ **/
+#[post("/me/email/verification")]
+pub async fn send_verification(
+    config: &ConfigState,
+    database: &DatabaseState,
+    mailer: &MailerState,
+    authorization: RequireScope<AccountWrite>,
+) -> Result<Status, Status> {
+    let mailer = mailer.as_ref()
+        .ok_or(Status::ServiceUnavailable)?;
+    let account = &authorization.account;
+    let Some(email) = account.email.as_ref().filter(|_| account.email_verified.is_none()) else {
+        return Err(Status::Conflict);
+    };
+    if is_cooling_down(database, account.id, "EmailVerification", config.verification_cooldown_millis()).await? {
+        return Err(Status::TooManyRequests);
+    }
+
+    let filter = doc! {
+        "account": account.id,
+        "purpose.EmailVerification": { "$exists": true },
+    };
+    database.collections.mail_token.delete_many(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let token = secret::generate(secret::BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let expiry = Utc::now() + Duration::milliseconds(config.verification_timeout_millis());
+    let purpose = Purpose::EmailVerification { email: email.clone() };
+    let mail_token = MailToken::new(
+        account.id, secret::hash(&token), purpose, DateTime::from_millis(expiry.timestamp_millis()),
+    );
+    database.collections.mail_token.insert_one(&mail_token)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let link = with_token(&config.verification_url(), &token);
+    let body = format!(
+        "Hi {},\n\nOpen the link below to verify your email address:\n{}\n\nThe link expires at {}.\n",
+        account.username, link, expiry.to_rfc2822(),
+    );
+    mailer.send_in_background(email.clone(), "Verify your email address", body);
+
+    Ok(Status::Accepted)
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /account/email/verification HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "token": "<Token>"
+ * }
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Every token is redeemable once only.
+ * Responded with 401 Unauthorized for an invalid or expired token.
+ **/
+#[post("/email/verification", data = "<json_request_body>")]
+pub async fn verify(
+    database: &DatabaseState,
+    json_request_body: Json<VerifyRequest>,
+) -> Result<Status, Status> {
+    let verify_request = json_request_body.into_inner();
+
+    let filter = doc! {
+        "hash": secret::hash(&verify_request.token),
+        "purpose.EmailVerification": { "$exists": true },
+        "expiry": { "$gte": DateTime::now() },
+    };
+    let mail_token = database.collections.mail_token.find_one_and_delete(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+    let Purpose::EmailVerification { email } = mail_token.purpose else {
+        return Err(Status::Unauthorized);
+    };
+
+    // The address may have been changed since the link is sent
+    let filter = doc! {
+        "_id": mail_token.account,
+        "email": email,
+    };
+    let update = doc! {
+        "$set": { "email_verified": Utc::now().timestamp_millis() },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Unauthorized);
+    }
+
+    Ok(Status::NoContent)
+}
+
+/**
+ * [url] with the query parameter "token".
+ **/
+/**
+ * Whether a mail token of [purpose] has been created for [account_id] within [cooldown_millis].
+ **/
+pub(in crate::rest) async fn is_cooling_down(
+    database: &Database,
+    account_id: ObjectId,
+    purpose: &str,
+    cooldown_millis: i64,
+) -> Result<bool, Status> {
+    let cooldown_start = Utc::now() - Duration::milliseconds(cooldown_millis);
+    let filter = cooldown_filter(account_id, purpose, cooldown_start.timestamp());
+    let recent_count = database.collections.mail_token.count_documents(filter)
+        .limit(1)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(recent_count > 0)
+}
+
+/**
+ * Filter of the mail tokens of [purpose] for [account_id] created since [start_seconds],
+ * told by their ids, which carry the creation time in seconds.
+ **/
+fn cooldown_filter(account_id: ObjectId, purpose: &str, start_seconds: i64) -> Document {
+    let start_id = ObjectId::from_parts(u32::try_from(start_seconds).unwrap_or(0), [0; 5], [0; 3]);
+    let mut filter = doc! {
+        "_id": { "$gte": start_id },
+        "account": account_id,
+    };
+    filter.insert(format!("purpose.{purpose}"), doc! { "$exists": true });
+    filter
+}
+
+pub(in crate::rest) fn with_token(url: &str, token: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}token={token}")
+}
+
+// 24 hours
+const VERIFICATION_TIMEOUT: i64 = 24 * 60 * 60 * 1000;
+
+// 1 minute
+const VERIFICATION_COOLDOWN: i64 = 60 * 1000;
+
+const VERIFICATION_URL: &str = "http://localhost:8000/verify-email";
+
+/**
+ * Email verification config keys in [Config].
+ *
+ * Validity of a verification link in milliseconds: "account.email.verification-timeout",
+ * set as [VERIFICATION_TIMEOUT] if not specified.
+ *
+ * Minimum delay in milliseconds between the links sent to an account: "account.email.verification-cooldown",
+ * set as [VERIFICATION_COOLDOWN] if not specified.
+ *
+ * Page of the client opening the link: "account.email.verification-url",
+ * set as [VERIFICATION_URL] if not specified.
+ **/
+trait EmailVerification {
+    fn verification_timeout_millis(&self) -> i64;
+    fn verification_cooldown_millis(&self) -> i64;
+    fn verification_url(&self) -> String;
+}
+
+impl EmailVerification for Config {
+    fn verification_timeout_millis(&self) -> i64 {
+        self.get(str_vec!["account", "email", "verification-timeout"])
+            .and_then(|timeout| timeout.parse::<i64>().ok())
+            .filter(|timeout| *timeout > 0)
+            .unwrap_or(VERIFICATION_TIMEOUT)
+    }
+
+    fn verification_cooldown_millis(&self) -> i64 {
+        self.get(str_vec!["account", "email", "verification-cooldown"])
+            .and_then(|cooldown| cooldown.parse::<i64>().ok())
+            .filter(|cooldown| *cooldown >= 0)
+            .unwrap_or(VERIFICATION_COOLDOWN)
+    }
+
+    fn verification_url(&self) -> String {
+        self.get(str_vec!["account", "email", "verification-url"])
+            .cloned()
+            .unwrap_or(VERIFICATION_URL.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verification_config() {
+        let config = Config::of_pairs(&[]);
+        assert_eq!(config.verification_timeout_millis(), VERIFICATION_TIMEOUT);
+        assert_eq!(config.verification_cooldown_millis(), VERIFICATION_COOLDOWN);
+        assert_eq!(config.verification_url(), VERIFICATION_URL);
+
+        let config = Config::of_pairs(&[
+            ("account.email.verification-timeout", "0"),
+            ("account.email.verification-cooldown", "0"),
+            ("account.email.verification-url", "https://example.com/verify"),
+        ]);
+        assert_eq!(config.verification_timeout_millis(), VERIFICATION_TIMEOUT);
+        assert_eq!(config.verification_cooldown_millis(), 0);
+        assert_eq!(config.verification_url(), "https://example.com/verify");
+    }
+
+    #[test]
+    fn test_cooldown_filter() {
+        let account_id = ObjectId::new();
+        let filter = cooldown_filter(account_id, "MagicLink", 1_700_000_000);
+        let start_id = ObjectId::from_parts(1_700_000_000, [0; 5], [0; 3]);
+        assert_eq!(filter, doc! {
+            "_id": { "$gte": start_id },
+            "account": account_id,
+            "purpose.MagicLink": { "$exists": true },
+        });
+        // Tokens created within the same second are matched
+        assert!(ObjectId::from_parts(1_700_000_000, [0xFF; 5], [0xFF; 3]) >= start_id);
+    }
+
+    #[test]
+    fn test_link() {
+        assert_eq!(with_token("https://example.com/verify", "abc"), "https://example.com/verify?token=abc");
+        assert_eq!(with_token("https://example.com/?page=verify", "abc"), "https://example.com/?page=verify&token=abc");
+    }
+}
@@ -0,0 +1,116 @@
+#![allow(private_interfaces)]
+use chrono::{Duration, Utc};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::{
+        database::collection::Invitation,
+        secret,
+        AdminAuthorization,
+        Config,
+        ConfigState,
+        DatabaseState,
+    },
+    str_vec,
+};
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    #[serde(default)]
+    expiry: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct CreateResponse {
+    id: String,
+    code: String,
+    expiry: i64,
+}
+
+// 16 random bytes, 32 hex characters
+const CODE_BYTES: usize = 16;
+
+/**
+ * Issue a single-use invitation code for [super::registration::register].
+ *
+ * Request:
+ * ```text
+ * POST /account/invitations HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "expiry": <Expiry-Timestamp-Milliseconds>
+ * }
+ * ```
+ * Where [expiry] is set as "account.invitation.duration" from now if not specified.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 201 Created
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "id": "<ObjectId-Hex>",
+ *     "code": "<Invitation-Code>",
+ *     "expiry": <Expiry-Timestamp-Milliseconds>
+ * }
+ * ```
+ * The code is responded once only, and only its hash is stored.
+ *
+ * Only admins can issue invitations.
+ **/
+#[post("/invitations", data = "<json_request_body>")]
+pub async fn create(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: AdminAuthorization,
+    json_request_body: Json<CreateRequest>,
+) -> Result<(Status, Json<CreateResponse>), Status> {
+    let create_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    let now_timestamp = Utc::now();
+    let expiry = create_request.expiry
+        .unwrap_or((now_timestamp + Duration::milliseconds(config.invitation_duration_millis())).timestamp_millis());
+    if expiry <= now_timestamp.timestamp_millis() {
+        return Err(Status::BadRequest);
+    }
+
+    let code = secret::generate(CODE_BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let invitation = Invitation::new(secret::hash(&code), account.id, expiry);
+    database.collections.invitation.insert_one(&invitation)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok((Status::Created, Json(CreateResponse {
+        id: invitation.id.to_hex(),
+        code,
+        expiry,
+    })))
+}
+
+// 7 days
+const INVITATION_DURATION: i64 = 7 * 24 * 60 * 60 * 1000;
+
+trait InvitationConfig {
+    fn invitation_duration_millis(&self) -> i64;
+}
+
+impl InvitationConfig for Config {
+    /**
+     * Default validity of an invitation in milliseconds: "account.invitation.duration",
+     * set as [INVITATION_DURATION] if not specified.
+     **/
+    fn invitation_duration_millis(&self) -> i64 {
+        self.get(str_vec!["account", "invitation", "duration"])
+            .and_then(|duration| duration.parse::<i64>().ok())
+            .filter(|duration| *duration > 0)
+            .unwrap_or(INVITATION_DURATION)
+    }
+}
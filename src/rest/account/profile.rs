@@ -0,0 +1,301 @@
+#![allow(private_interfaces)]
+use mongodb::{
+    bson::{doc, Document},
+    options::ReturnDocument,
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    database::{
+        collection::{account::Role, Account},
+        is_duplicate_key,
+    },
+    scope::{AccountRead, AccountWrite},
+    ConfigState,
+    DatabaseState,
+    RequireScope,
+};
+
+use super::super::admin::bootstrap;
+
+#[derive(Serialize)]
+struct ProfileResponse {
+    id: String,
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email_verified: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avatar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    role: Role,
+    mfa_required: bool,
+}
+
+impl From<Account> for ProfileResponse {
+    fn from(account: Account) -> Self {
+        Self {
+            id: account.id.to_hex(),
+            username: account.username,
+            display_name: account.display_name,
+            email: account.email,
+            email_verified: account.email_verified,
+            avatar: account.avatar,
+            locale: account.locale,
+            timezone: account.timezone,
+            role: account.role,
+            mfa_required: account.mfa_required,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateRequest {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    avatar: Option<String>,
+    #[serde(default)]
+    locale: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+/**
+ * Request:
+ * ```text
+ * GET /account/me HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "id": "<ObjectId-Hex>",
+ *     "username": "<Username>",
+ *     "display_name": "<Display-Name>",
+ *     "email": "<Email-Address>",
+ *     "email_verified": <Verification-Timestamp-Milliseconds>,
+ *     "avatar": "<Avatar-Reference>",
+ *     "locale": "<BCP-47-Language-Tag>",
+ *     "timezone": "<IANA-Time-Zone>",
+ *     "role": "Admin" | "User" | "Readonly",
+ *     "mfa_required": <true|false>
+ * }
+ * ```
+ **/
+#[get("/me")]
+pub async fn me(authorization: RequireScope<AccountRead>) -> Json<ProfileResponse> {
+    Json(ProfileResponse::from(authorization.into_inner().account))
+}
+
+/**
+ * Request:
+ * ```text
+ * PATCH /account/me HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "username": "<Username>",
+ *     "display_name": "<Display-Name>",
+ *     ...
+ * }
+ * ```
+ * Where only the specified fields are updated, and an empty string clears the field,
+ * except for [username] which cannot be cleared.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * <Profile as in GET /account/me>
+ * ```
+ *
+ * Responded with 400 Bad Request for an invalid field, and 409 Conflict for a taken username
+ * or one listed in "account.admins".
+ * Changing the username requires a session logged in recently, otherwise 403 Forbidden is responded,
+ * as logins by the previous username stop working at once.
+ * Changing the email address resets its verification and invalidates the links sent to the previous one.
+ **/
+#[patch("/me", data = "<json_request_body>")]
+pub async fn update(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<UpdateRequest>,
+) -> Result<Json<ProfileResponse>, Status> {
+    let update_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    let mut set = Document::new();
+    let mut unset = Document::new();
+
+    let username = update_request.username
+        .map(|username| username.trim().to_string())
+        .filter(|username| *username != account.username);
+    if let Some(username) = &username {
+        if !Account::is_valid_username(username) {
+            return Err(Status::BadRequest);
+        }
+        if !authorization.is_recent_login() {
+            return Err(Status::Forbidden);
+        }
+        // Reserved for the admin bootstrap
+        if bootstrap::is_listed(config, username) {
+            return Err(Status::Conflict);
+        }
+        set.insert("username", username);
+    }
+
+    let fields = [
+        ("display_name", update_request.display_name, is_valid_display_name as fn(&str) -> bool),
+        ("email", update_request.email, is_valid_email),
+        ("avatar", update_request.avatar, is_valid_avatar),
+        ("locale", update_request.locale, is_valid_locale),
+        ("timezone", update_request.timezone, is_valid_timezone),
+    ];
+    let mut is_email_changed = false;
+    for (key, value, is_valid) in fields {
+        let Some(value) = value.map(|value| value.trim().to_string()) else {
+            continue;
+        };
+        if key == "email" && account.email.as_deref() != Some(value.as_str()) {
+            is_email_changed = true;
+            unset.insert("email_verified", "");
+        }
+        if value.is_empty() {
+            unset.insert(key, "");
+        } else if is_valid(&value) {
+            set.insert(key, value);
+        } else {
+            return Err(Status::BadRequest);
+        }
+    }
+
+    let mut update = Document::new();
+    if !set.is_empty() {
+        update.insert("$set", set);
+    }
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    if update.is_empty() {
+        return Ok(Json(ProfileResponse::from(authorization.into_inner().account)));
+    }
+
+    // Never overwrite a username changed in the meantime, the unique index guards the new one
+    let filter = doc! {
+        "_id": account.id,
+        "username": &account.username,
+    };
+    let updated_account = database.collections.account.find_one_and_update(filter, update)
+        .return_document(ReturnDocument::After)
+        .await
+        .map_err(|err| if is_duplicate_key(&err) { Status::Conflict } else { Status::InternalServerError })?
+        .ok_or(Status::Conflict)?;
+
+    if username.is_some() {
+        // Pending challenges are issued for the previous username
+        let filter = doc! { "username": &account.username };
+        database.collections.challenge.delete_many(filter)
+            .await
+            .map_err(|_| Status::InternalServerError)?;
+    }
+
+    if is_email_changed {
+        let filter = doc! { "account": account.id };
+        database.collections.mail_token.delete_many(filter)
+            .await
+            .map_err(|_| Status::InternalServerError)?;
+    }
+
+    Ok(Json(ProfileResponse::from(updated_account)))
+}
+
+fn is_valid_display_name(display_name: &str) -> bool {
+    display_name.chars().count() <= 64 && !display_name.chars().any(char::is_control)
+}
+
+/**
+ * Loose syntax check only, the address is not verified to be deliverable.
+ **/
+fn is_valid_email(email: &str) -> bool {
+    let Some((local_part, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    email.len() <= 254
+        && !local_part.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(|char| char.is_whitespace() || char.is_control())
+}
+
+fn is_valid_avatar(avatar: &str) -> bool {
+    avatar.len() <= 2048 && !avatar.chars().any(|char| char.is_whitespace() || char.is_control())
+}
+
+fn is_valid_locale(locale: &str) -> bool {
+    (2..=35).contains(&locale.len())
+        && locale.split('-').all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|char| char.is_ascii_alphanumeric())
+        })
+}
+
+fn is_valid_timezone(timezone: &str) -> bool {
+    (1..=64).contains(&timezone.len())
+        && timezone.chars().all(|char| char.is_ascii_alphanumeric() || matches!(char, '/' | '_' | '-' | '+'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_email() {
+        assert!(is_valid_email("user@example.com"));
+        assert!(is_valid_email("first.last+tag@mail.example.org"));
+        assert!(!is_valid_email("user"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("user@localhost"));
+        assert!(!is_valid_email("user@example.com."));
+        assert!(!is_valid_email("us er@example.com"));
+    }
+
+    #[test]
+    fn test_locale() {
+        assert!(is_valid_locale("en"));
+        assert!(is_valid_locale("en-US"));
+        assert!(is_valid_locale("zh-Hant-TW"));
+        assert!(!is_valid_locale("e"));
+        assert!(!is_valid_locale("en_US"));
+        assert!(!is_valid_locale("en--US"));
+    }
+
+    #[test]
+    fn test_timezone() {
+        assert!(is_valid_timezone("UTC"));
+        assert!(is_valid_timezone("America/Argentina/Buenos_Aires"));
+        assert!(is_valid_timezone("Etc/GMT+8"));
+        assert!(!is_valid_timezone("Europe/London; DROP"));
+    }
+}
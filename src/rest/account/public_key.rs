@@ -0,0 +1,458 @@
+#![allow(private_interfaces)]
+use chrono::Utc;
+use mongodb::{
+    bson,
+    bson::{doc, oid::ObjectId, Document},
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    database::collection::{
+        account::public_key::{KeyType, Scheme, Validity},
+        account::PublicKey,
+        token::Issuer,
+        Account,
+    },
+    scope::{AccountRead, AccountWrite},
+    Authorization,
+    DatabaseState,
+    RequireScope,
+};
+
+use super::super::auth::signature::verifier;
+
+#[derive(Serialize)]
+struct PublicKeyResponse {
+    id: String,
+    key: String,
+    key_type: KeyType,
+    scheme: Scheme,
+    validity: Validity,
+}
+
+impl From<&PublicKey> for PublicKeyResponse {
+    fn from(public_key: &PublicKey) -> Self {
+        Self {
+            id: public_key.id.to_hex(),
+            key: public_key.key.clone(),
+            key_type: public_key.key_type,
+            scheme: public_key.scheme,
+            validity: public_key.validity,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AddRequest {
+    key: String,
+    #[serde(default)]
+    scheme: Option<Scheme>,
+    #[serde(default)]
+    validity: Option<Validity>,
+}
+
+#[derive(Deserialize)]
+struct UpdateRequest {
+    #[serde(default)]
+    expiry: Option<i64>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+/**
+ * Request:
+ * ```text
+ * GET /account/keys HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * [
+ *     {
+ *         "id": "<ObjectId-Hex>",
+ *         "key": "<PEM or OpenSSH Public Key>",
+ *         "key_type": "Rsa" | "Ed25519" | "EcdsaP256" | "EcdsaP384",
+ *         "scheme": "RsaRaw" | "RsaPkcs1Sha256" | "RsaPssSha256",
+ *         "validity": "Master" | "Permanent" | { "Temporary": <Expiry> } | { "Disabled": <Timestamp> }
+ *     },
+ *     ...
+ * ]
+ * ```
+ **/
+#[get("/keys")]
+pub async fn list(authorization: RequireScope<AccountRead>) -> Json<Vec<PublicKeyResponse>> {
+    let public_keys = authorization.account.public_keys.iter()
+        .map(PublicKeyResponse::from)
+        .collect();
+    Json(public_keys)
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /account/keys HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "key": "<PEM or OpenSSH Public Key>",
+ *     "scheme": "RsaPkcs1Sha256" | "RsaPssSha256" | "RsaRaw",
+ *     "validity": "Master" | "Permanent" | { "Temporary": <Expiry> }
+ * }
+ * ```
+ * Where [scheme] is set as "RsaPkcs1Sha256" and [validity] as "Permanent" if not specified.
+ * Key type is detected from [key].
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 201 Created
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * <Public Key as in GET /account/keys>
+ * ```
+ *
+ * Only sessions issued by a master key can add keys, unless the account has no master key yet,
+ * when adding a key requires a session logged in recently, never a personal access token.
+ * Responded with 409 Conflict if a master key is added in the meantime.
+ **/
+#[post("/keys", data = "<json_request_body>")]
+pub async fn add(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<AddRequest>,
+) -> Result<(Status, Json<PublicKeyResponse>), Status> {
+    let add_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    let validity = add_request.validity.unwrap_or(Validity::Permanent);
+    match validity {
+        Validity::Disabled(_) => return Err(Status::BadRequest),
+        Validity::Temporary(expiry) if expiry <= Utc::now().timestamp_millis() => {
+            return Err(Status::BadRequest);
+        }
+        _ => {}
+    }
+    if !may_add(&authorization) {
+        return Err(Status::Forbidden);
+    }
+
+    let key = verifier::load(&add_request.key)
+        .ok_or(Status::BadRequest)?;
+    let key_type = verifier::key_type_of(&key)
+        .ok_or(Status::BadRequest)?;
+    let is_registered = account.public_keys.iter()
+        .filter_map(|public_key| verifier::load(&public_key.key))
+        .any(|registered_key| registered_key.public_eq(&key));
+    if is_registered {
+        return Err(Status::Conflict);
+    }
+
+    let scheme = add_request.scheme.unwrap_or(Scheme::RsaPkcs1Sha256);
+
+    let public_key = PublicKey::new(add_request.key.trim().to_string(), validity, key_type, scheme);
+    let public_key_document = bson::to_bson(&public_key)
+        .map_err(|_| Status::InternalServerError)?;
+    let mut filter = doc! { "_id": account.id };
+    if !has_master_key(account) {
+        // Never add past a master key added in the meantime, which requires a master session
+        let master = bson::to_bson(&Validity::Master)
+            .map_err(|_| Status::InternalServerError)?;
+        filter.insert("public_keys.validity", doc! { "$ne": master });
+    }
+    let update = doc! {
+        "$push": { "public_keys": public_key_document },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Conflict);
+    }
+
+    Ok((Status::Created, Json(PublicKeyResponse::from(&public_key))))
+}
+
+/**
+ * Request:
+ * ```text
+ * PATCH /account/keys/<ObjectId-Hex> HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "expiry": <Expiry>,
+ *     "disabled": true
+ * }
+ * ```
+ * Where either [expiry] makes the key temporary until the timestamp in milliseconds,
+ * or [disabled] disables the key. Disabled keys are never enabled again.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * <Public Key as in GET /account/keys>
+ * ```
+ *
+ * Only sessions issued by a master key can update keys, and the last master key cannot be updated.
+ * Responded with 409 Conflict for setting the expiry of a disabled key.
+ **/
+#[patch("/keys/<id>", data = "<json_request_body>")]
+pub async fn update(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    id: &str,
+    json_request_body: Json<UpdateRequest>,
+) -> Result<Json<PublicKeyResponse>, Status> {
+    let update_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    if !is_master_session(&authorization) {
+        return Err(Status::Forbidden);
+    }
+
+    let public_key = find_public_key(account, id)?;
+    let validity = updated_validity(public_key.validity, &update_request, Utc::now().timestamp_millis())?;
+
+    let validity_document = bson::to_bson(&validity)
+        .map_err(|_| Status::InternalServerError)?;
+    let mut filter = public_key_filter(account, public_key)?;
+    if matches!(validity, Validity::Temporary(_)) {
+        // Never enable a key disabled in the meantime
+        filter.insert("$nor", vec![doc! {
+            "public_keys": { "$elemMatch": { "_id": public_key.id, "validity.Disabled": { "$exists": true } } },
+        }]);
+    }
+    let update = doc! {
+        "$set": { "public_keys.$[key].validity": validity_document },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .array_filters(vec![doc! { "key._id": public_key.id }])
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        // Key is removed or disabled, or is the last master key
+        return Err(Status::Conflict);
+    }
+
+    let mut public_key_response = PublicKeyResponse::from(public_key);
+    public_key_response.validity = validity;
+    Ok(Json(public_key_response))
+}
+
+/**
+ * Request:
+ * ```text
+ * DELETE /account/keys/<ObjectId-Hex> HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Only sessions issued by a master key can remove keys, and the last master key cannot be removed.
+ **/
+#[delete("/keys/<id>")]
+pub async fn remove(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    id: &str,
+) -> Result<Status, Status> {
+    let account = &authorization.account;
+
+    if !is_master_session(&authorization) {
+        return Err(Status::Forbidden);
+    }
+
+    let public_key = find_public_key(account, id)?;
+    let filter = public_key_filter(account, public_key)?;
+    let update = doc! {
+        "$pull": { "public_keys": { "_id": public_key.id } },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        // Key is removed, or is the last master key
+        return Err(Status::Conflict);
+    }
+
+    Ok(Status::NoContent)
+}
+
+/**
+ * Whether [authorization] can add a key: a session of a master key if the account has one,
+ * otherwise a session logged in recently, as any key added becomes a login of the account.
+ **/
+fn may_add(authorization: &Authorization) -> bool {
+    if has_master_key(&authorization.account) {
+        is_master_session(authorization)
+    } else {
+        authorization.is_recent_login()
+    }
+}
+
+/**
+ * Validity of a key currently of [validity] after [update_request].
+ **/
+fn updated_validity(validity: Validity, update_request: &UpdateRequest, now_timestamp: i64) -> Result<Validity, Status> {
+    match (update_request.disabled, update_request.expiry) {
+        (true, _) => Ok(Validity::Disabled(now_timestamp)),
+        (false, Some(_)) if matches!(validity, Validity::Disabled(_)) => Err(Status::Conflict),
+        (false, Some(expiry)) if expiry > now_timestamp => Ok(Validity::Temporary(expiry)),
+        _ => Err(Status::BadRequest),
+    }
+}
+
+fn has_master_key(account: &Account) -> bool {
+    account.public_keys.iter()
+        .any(|public_key| matches!(public_key.validity, Validity::Master))
+}
+
+/**
+ * Whether the session is issued by a key that is currently a master key of the account.
+ **/
+fn is_master_session(authorization: &Authorization) -> bool {
+    let Some(Issuer::PublicKey(public_key_id)) = authorization.session().map(|token| token.issuer.first_factor()) else {
+        return false;
+    };
+    authorization.account.public_keys.iter()
+        .any(|public_key| public_key.id == *public_key_id && matches!(public_key.validity, Validity::Master))
+}
+
+fn find_public_key<'a>(account: &'a Account, id: &str) -> Result<&'a PublicKey, Status> {
+    let id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+    account.public_keys.iter()
+        .find(|public_key| public_key.id == id)
+        .ok_or(Status::NotFound)
+}
+
+/**
+ * Filter matching [public_key] of [account]. If it is a master key,
+ * the filter matches only while another master key remains.
+ **/
+fn public_key_filter(account: &Account, public_key: &PublicKey) -> Result<Document, Status> {
+    let master = bson::to_bson(&Validity::Master)
+        .map_err(|_| Status::InternalServerError)?;
+    let mut filter = doc! {
+        "_id": account.id,
+        "public_keys._id": public_key.id,
+    };
+    if matches!(public_key.validity, Validity::Master) {
+        filter.insert("public_keys", doc! {
+            "$elemMatch": { "_id": { "$ne": public_key.id }, "validity": master },
+        });
+    }
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::{
+        authorization::Credential,
+        database::collection::{AccessToken, Token},
+    };
+
+    use super::*;
+
+    fn account_with(validities: &[Validity]) -> Account {
+        let public_keys = validities.iter()
+            .map(|validity| PublicKey::new("<Key>".into(), *validity, KeyType::Ed25519, Scheme::default()))
+            .collect();
+        Account::new(ObjectId::new(), "user".into(), public_keys, None, Vec::new())
+    }
+
+    fn session_of(account: Account, token_id: ObjectId, issuer: Issuer) -> Authorization {
+        let token = Token::new(token_id, account.id, 0, issuer);
+        Authorization { credential: Credential::Session(token), account }
+    }
+
+    fn access_token_of(account: Account) -> Authorization {
+        let access_token = AccessToken::new(account.id, "CI".into(), "<Hash>".into(), None, None);
+        Authorization { credential: Credential::AccessToken(access_token), account }
+    }
+
+    // Issued an hour ago
+    fn stale_token_id() -> ObjectId {
+        let seconds = (Utc::now().timestamp() - 60 * 60) as u32;
+        ObjectId::from_parts(seconds, [0; 5], [0; 3])
+    }
+
+    #[test]
+    fn test_may_add_with_master_key() {
+        let account = account_with(&[Validity::Master, Validity::Permanent]);
+        let master_key_id = account.public_keys[0].id;
+        let permanent_key_id = account.public_keys[1].id;
+
+        let authorization = session_of(account, stale_token_id(), Issuer::PublicKey(master_key_id));
+        assert!(may_add(&authorization));
+
+        let authorization = session_of(authorization.account, ObjectId::new(), Issuer::PublicKey(permanent_key_id));
+        assert!(!may_add(&authorization));
+
+        let authorization = access_token_of(authorization.account);
+        assert!(!may_add(&authorization));
+    }
+
+    #[test]
+    fn test_may_add_without_master_key() {
+        let account = account_with(&[Validity::Permanent]);
+
+        let authorization = session_of(account, ObjectId::new(), Issuer::Password);
+        assert!(may_add(&authorization));
+
+        let authorization = session_of(authorization.account, stale_token_id(), Issuer::Password);
+        assert!(!may_add(&authorization));
+
+        // Personal access tokens never add a login
+        let authorization = access_token_of(authorization.account);
+        assert!(!may_add(&authorization));
+    }
+
+    #[test]
+    fn test_updated_validity() {
+        let now_timestamp = Utc::now().timestamp_millis();
+        let expire = UpdateRequest { expiry: Some(now_timestamp + 1000), disabled: false };
+        let expired = UpdateRequest { expiry: Some(now_timestamp - 1000), disabled: false };
+        let disable = UpdateRequest { expiry: None, disabled: true };
+        let empty = UpdateRequest { expiry: None, disabled: false };
+
+        assert!(matches!(
+            updated_validity(Validity::Permanent, &expire, now_timestamp),
+            Ok(Validity::Temporary(expiry)) if expiry == now_timestamp + 1000
+        ));
+        assert!(matches!(
+            updated_validity(Validity::Permanent, &disable, now_timestamp),
+            Ok(Validity::Disabled(timestamp)) if timestamp == now_timestamp
+        ));
+        assert_eq!(updated_validity(Validity::Disabled(0), &expire, now_timestamp).err(), Some(Status::Conflict));
+        assert_eq!(updated_validity(Validity::Permanent, &expired, now_timestamp).err(), Some(Status::BadRequest));
+        assert_eq!(updated_validity(Validity::Permanent, &empty, now_timestamp).err(), Some(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_public_key_filter() {
+        let account = account_with(&[Validity::Master, Validity::Permanent]);
+
+        let filter = public_key_filter(&account, &account.public_keys[0]).unwrap();
+        assert!(filter.contains_key("public_keys"));
+
+        let filter = public_key_filter(&account, &account.public_keys[1]).unwrap();
+        assert!(!filter.contains_key("public_keys"));
+        assert_eq!(filter.get_object_id("public_keys._id").unwrap(), account.public_keys[1].id);
+    }
+}
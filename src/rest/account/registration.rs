@@ -0,0 +1,339 @@
+#![allow(private_interfaces)]
+use chrono::Utc;
+use mongodb::bson::{doc, oid::ObjectId};
+use openssl::memcmp;
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::{
+        database::{
+            collection::{
+                account::{
+                    public_key::{Scheme, Validity},
+                    OnetimePasswordSecret,
+                    PublicKey,
+                    Role,
+                },
+                Account,
+            },
+            is_duplicate_key,
+        },
+        secret,
+        Config,
+        ConfigState,
+        Database,
+        DatabaseState,
+    },
+    str_vec,
+};
+
+use super::super::{
+    admin::bootstrap,
+    auth::{onetime_password, signature::verifier},
+};
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    usr: String,
+    #[serde(default)]
+    invitation: Option<String>,
+    #[serde(default)]
+    credential: Option<Credential>,
+}
+
+/**
+ * Initial credential of the account, either a master public key or a TOTP secret.
+ **/
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Credential {
+    PublicKey {
+        key: String,
+        #[serde(default)]
+        scheme: Option<Scheme>,
+    },
+    Otp,
+}
+
+#[derive(Serialize)]
+struct RegisterResponse {
+    id: String,
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    otp: Option<OtpResponse>,
+}
+
+#[derive(Serialize)]
+struct OtpResponse {
+    secret: String,
+    uri: String,
+    registration: String,
+}
+
+#[derive(Deserialize)]
+struct ConfirmationRequest {
+    usr: String,
+    registration: String,
+    otp: String,
+}
+
+#[derive(Serialize)]
+struct ConfirmationResponse {
+    recovery_codes: Vec<String>,
+}
+
+enum RegistrationMode {
+    Open,
+    InviteOnly,
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /account/register HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "usr": "<Username>",
+ *     "invitation": "<Invitation-Code>",
+ *     "credential": { "public_key": { "key": "<PEM or OpenSSH Public Key>", "scheme": "<Scheme>" } } | "otp"
+ * }
+ * ```
+ * Where [invitation] is required only if "account.registration.mode" is "invite-only",
+ * and [scheme] is set as "RsaPkcs1Sha256" if not specified.
+ * A username listed in "account.admins" registers as an admin without invitation while no admin exists.
+ * The public key is registered as the master key, while the TOTP secret is enrolled pending
+ * and responded once only, along with the registration secret to confirm it with [confirm]
+ * before logging in with it.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 201 Created
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "id": "<ObjectId-Hex>",
+ *     "username": "<Username>",
+ *     "otp": {
+ *         "secret": "<Base32-Secret>",
+ *         "uri": "otpauth://totp/<Issuer>:<Username>?secret=<Base32-Secret>&...",
+ *         "registration": "<Registration-Secret>"
+ *     }
+ * }
+ * ```
+ *
+ * Responded with 400 Bad Request for an invalid username or credential,
+ * 403 Forbidden for a missing, expired or used invitation, and 409 Conflict for a taken username.
+ **/
+#[post("/register", data = "<json_request_body>")]
+pub async fn register(
+    config: &ConfigState,
+    database: &DatabaseState,
+    json_request_body: Json<RegisterRequest>,
+) -> Result<(Status, Json<RegisterResponse>), Status> {
+    let register_request = json_request_body.into_inner();
+    let username = register_request.usr.trim().to_string();
+    if !Account::is_valid_username(&username) {
+        return Err(Status::BadRequest);
+    }
+
+    let account_id = ObjectId::new();
+    let mut account = Account::new(account_id, username, Vec::new(), None, Vec::new());
+    let otp = match register_request.credential.ok_or(Status::BadRequest)? {
+        Credential::PublicKey { key, scheme } => {
+            let loaded_key = verifier::load(&key)
+                .ok_or(Status::BadRequest)?;
+            let key_type = verifier::key_type_of(&loaded_key)
+                .ok_or(Status::BadRequest)?;
+            let scheme = scheme.unwrap_or(Scheme::RsaPkcs1Sha256);
+            account.public_keys.push(PublicKey::new(key.trim().to_string(), Validity::Master, key_type, scheme));
+            None
+        }
+        Credential::Otp => {
+            let (secret, uri) = onetime_password::generate_secret(config, &account.username)?;
+            let registration = secret::generate(secret::BYTES)
+                .map_err(|_| Status::InternalServerError)?;
+            let mut onetime_password_secret = OnetimePasswordSecret::pending(secret.clone());
+            onetime_password_secret.registration = Some(secret::hash(&registration));
+            account.onetime_password_secret = Some(onetime_password_secret);
+            Some(OtpResponse { secret, uri, registration })
+        }
+    };
+
+    // Fail fast before consuming the invitation, the unique index still guards the insertion
+    let is_taken = database.find_account_by_username(&account.username)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .is_some();
+    if is_taken {
+        return Err(Status::Conflict);
+    }
+
+    let is_first_admin = bootstrap::is_first_admin(config, database, &account.username)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if is_first_admin {
+        account.role = Role::Admin;
+    }
+
+    let invitation_id = match config.registration_mode() {
+        RegistrationMode::Open => None,
+        RegistrationMode::InviteOnly if is_first_admin => None,
+        RegistrationMode::InviteOnly => {
+            let code = register_request.invitation.as_deref()
+                .ok_or(Status::Forbidden)?;
+            Some(redeem(database, code, account_id).await?)
+        }
+    };
+
+    if let Err(err) = database.collections.account.insert_one(&account).await {
+        // Give the invitation back if the account is not created
+        if let Some(invitation_id) = invitation_id {
+            let filter = doc! { "_id": invitation_id };
+            let update = doc! { "$unset": { "redeemer": "" } };
+            database.collections.invitation.update_one(filter, update)
+                .await
+                .map_err(|_| Status::InternalServerError)?;
+        }
+        return Err(if is_duplicate_key(&err) { Status::Conflict } else { Status::InternalServerError });
+    }
+
+    Ok((Status::Created, Json(RegisterResponse {
+        id: account_id.to_hex(),
+        username: account.username,
+        otp,
+    })))
+}
+
+/**
+ * Confirm the TOTP secret enrolled by [register] with a first valid code,
+ * so that the account can log in with one-time passwords.
+ * The registration secret responded by [register] binds the confirmation to the registering client,
+ * so that the code of a pending account cannot be guessed by anyone else.
+ *
+ * Request:
+ * ```text
+ * POST /account/register/confirmation HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "usr": "<Username>",
+ *     "registration": "<Registration-Secret>",
+ *     "otp": "<One-time Password>"
+ * }
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "recovery_codes": ["<Recovery-Code>", ...]
+ * }
+ * ```
+ *
+ * Responded with 401 Unauthorized for an invalid code,
+ * and 404 Not Found if the account has no pending secret of the registration secret to confirm.
+ **/
+#[post("/register/confirmation", data = "<json_request_body>")]
+pub async fn confirm(
+    config: &ConfigState,
+    database: &DatabaseState,
+    json_request_body: Json<ConfirmationRequest>,
+) -> Result<Json<ConfirmationResponse>, Status> {
+    let confirmation_request = json_request_body.into_inner();
+
+    let account = database.find_account_by_username(&confirmation_request.usr)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|account| account.is_enabled())
+        .filter(|account| is_registration_of(account, &confirmation_request.registration))
+        .ok_or(Status::NotFound)?;
+
+    let recovery_codes = onetime_password::confirm(config, database, &account, &confirmation_request.otp)
+        .await
+        .map_err(|status| if status == Status::Conflict { Status::NotFound } else { status })?;
+
+    Ok(Json(ConfirmationResponse { recovery_codes }))
+}
+
+/**
+ * Whether [registration] is the registration secret of the pending TOTP secret of [account].
+ **/
+fn is_registration_of(account: &Account, registration: &str) -> bool {
+    account.onetime_password_secret.as_ref()
+        .and_then(|otp_secret| otp_secret.registration.as_deref())
+        .is_some_and(|hash| {
+            let presented = secret::hash(registration);
+            hash.len() == presented.len() && memcmp::eq(hash.as_bytes(), presented.as_bytes())
+        })
+}
+
+/**
+ * Mark the unused and unexpired invitation of [code] as redeemed by [account_id],
+ * returning the id of the invitation.
+ **/
+async fn redeem(
+    database: &Database,
+    code: &str,
+    account_id: ObjectId,
+) -> Result<ObjectId, Status> {
+    let filter = doc! {
+        "hash": secret::hash(code),
+        "expiry": { "$gte": Utc::now().timestamp_millis() },
+        "redeemer": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "redeemer": account_id },
+    };
+    database.collections.invitation.find_one_and_update(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .map(|invitation| invitation.id)
+        .ok_or(Status::Forbidden)
+}
+
+trait Registration {
+    fn registration_mode(&self) -> RegistrationMode;
+}
+
+impl Registration for Config {
+    /**
+     * Who can register accounts: "account.registration.mode", either "open" or "invite-only",
+     * set as "invite-only" if not specified.
+     **/
+    fn registration_mode(&self) -> RegistrationMode {
+        match self.get(str_vec!["account", "registration", "mode"]).map(String::as_str) {
+            Some("open") => RegistrationMode::Open,
+            _ => RegistrationMode::InviteOnly,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_registration_of() {
+        let mut account = Account::new(ObjectId::new(), "user".into(), Vec::new(), None, Vec::new());
+        assert!(!is_registration_of(&account, "secret"));
+
+        // Secrets enrolled with a session are confirmed with the session only
+        account.onetime_password_secret = Some(OnetimePasswordSecret::pending("SECRET".into()));
+        assert!(!is_registration_of(&account, "secret"));
+
+        let mut otp_secret = OnetimePasswordSecret::pending("SECRET".into());
+        otp_secret.registration = Some(secret::hash("secret"));
+        account.onetime_password_secret = Some(otp_secret);
+        assert!(is_registration_of(&account, "secret"));
+        assert!(!is_registration_of(&account, "other"));
+        assert!(!is_registration_of(&account, ""));
+    }
+}
@@ -0,0 +1,20 @@
+use rocket::Route;
+
+mod account;
+
+pub(super) mod bootstrap;
+
+pub const MOUNT_POINT: &str = "/admin";
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        // GET /admin/accounts
+        account::list,
+        // POST /admin/accounts/<id>/disable
+        account::disable,
+        // POST /admin/accounts/<id>/enable
+        account::enable,
+        // PUT /admin/accounts/<id>/role
+        account::update_role,
+    ]
+}
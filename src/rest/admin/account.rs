@@ -0,0 +1,214 @@
+#![allow(private_interfaces)]
+use chrono::Utc;
+use mongodb::{
+    bson,
+    bson::{doc, oid::ObjectId},
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    database::collection::{account::Role, Account},
+    AdminAuthorization,
+    Database,
+    DatabaseState,
+};
+
+#[derive(Serialize)]
+struct AccountResponse {
+    id: String,
+    username: String,
+    creation: i64,
+    role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disabled: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deletion: Option<i64>,
+    mfa_required: bool,
+}
+
+impl From<Account> for AccountResponse {
+    fn from(account: Account) -> Self {
+        Self {
+            id: account.id.to_hex(),
+            username: account.username,
+            creation: account.id.timestamp().timestamp_millis(),
+            role: account.role,
+            disabled: account.disabled,
+            deletion: account.deletion,
+            mfa_required: account.mfa_required,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RoleRequest {
+    role: Role,
+}
+
+/**
+ * Request:
+ * ```text
+ * GET /admin/accounts HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * [
+ *     {
+ *         "id": "<ObjectId-Hex>",
+ *         "username": "<Username>",
+ *         "creation": <Timestamp-Milliseconds>,
+ *         "role": "Admin" | "User" | "Readonly",
+ *         "disabled": <Timestamp-Milliseconds>,
+ *         "deletion": <Purge-Timestamp-Milliseconds>,
+ *         "mfa_required": <true|false>
+ *     },
+ *     ...
+ * ]
+ * ```
+ **/
+#[get("/accounts")]
+pub async fn list(
+    database: &DatabaseState,
+    _authorization: AdminAuthorization,
+) -> Result<Json<Vec<AccountResponse>>, Status> {
+    let mut cursor = database.collections.account.find(doc! {})
+        .sort(doc! { "_id": 1 })
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let mut accounts = Vec::new();
+    while cursor.advance().await.map_err(|_| Status::InternalServerError)? {
+        let account = cursor.deserialize_current()
+            .map_err(|_| Status::InternalServerError)?;
+        accounts.push(AccountResponse::from(account));
+    }
+    Ok(Json(accounts))
+}
+
+/**
+ * Disable the account of <id>, failing the logins and the authorization of all its credentials
+ * until it is enabled again.
+ *
+ * Request:
+ * ```text
+ * POST /admin/accounts/<ObjectId-Hex>/disable HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Admins cannot disable their own accounts.
+ **/
+#[post("/accounts/<id>/disable")]
+pub async fn disable(
+    database: &DatabaseState,
+    authorization: AdminAuthorization,
+    id: &str,
+) -> Result<Status, Status> {
+    let id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+    if id == authorization.account.id {
+        return Err(Status::Conflict);
+    }
+
+    let filter = doc! { "_id": id };
+    let update = doc! {
+        "$set": { "disabled": Utc::now().timestamp_millis() },
+    };
+    update_account(database, filter, update).await
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /admin/accounts/<ObjectId-Hex>/enable HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ **/
+#[post("/accounts/<id>/enable")]
+pub async fn enable(
+    database: &DatabaseState,
+    _authorization: AdminAuthorization,
+    id: &str,
+) -> Result<Status, Status> {
+    let id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+
+    let filter = doc! { "_id": id };
+    let update = doc! {
+        "$unset": { "disabled": "" },
+    };
+    update_account(database, filter, update).await
+}
+
+/**
+ * Request:
+ * ```text
+ * PUT /admin/accounts/<ObjectId-Hex>/role HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "role": "Admin" | "User" | "Readonly"
+ * }
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Admins cannot change their own roles, so that an admin always remains.
+ **/
+#[put("/accounts/<id>/role", data = "<json_request_body>")]
+pub async fn update_role(
+    database: &DatabaseState,
+    authorization: AdminAuthorization,
+    id: &str,
+    json_request_body: Json<RoleRequest>,
+) -> Result<Status, Status> {
+    let role_request = json_request_body.into_inner();
+    let id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+    if id == authorization.account.id {
+        return Err(Status::Conflict);
+    }
+
+    let role = bson::to_bson(&role_request.role)
+        .map_err(|_| Status::InternalServerError)?;
+    let filter = doc! { "_id": id };
+    let update = doc! {
+        "$set": { "role": role },
+    };
+    update_account(database, filter, update).await
+}
+
+async fn update_account(
+    database: &Database,
+    filter: bson::Document,
+    update: bson::Document,
+) -> Result<Status, Status> {
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::NotFound);
+    }
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,121 @@
+use mongodb::{bson, bson::{doc, oid::ObjectId}, error::Result};
+use rocket::fairing::AdHoc;
+
+use crate::{
+    state::{
+        database::collection::account::Role,
+        Config,
+        Database,
+    },
+    str_vec,
+};
+
+/**
+ * Promote the existing accounts listed in "account.admins" to admins on the ignition of Rocket,
+ * only while no admin exists, so that the list bootstraps the first admins and grants nothing afterwards.
+ **/
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("Admin Bootstrap", |rocket| Box::pin(async move {
+        let admins = rocket.state::<Config>().unwrap().admins();
+        let database = rocket.state::<Database>().unwrap();
+        match promote(database, &admins).await {
+            Ok(0) => {}
+            Ok(count) => info!("Promoted {} accounts listed in \"account.admins\" to admins.", count),
+            Err(err) => warn!("Failed to promote the accounts listed in \"account.admins\" ({}).", err),
+        }
+        rocket
+    }))
+}
+
+async fn promote(database: &Database, admins: &[String]) -> Result<u64> {
+    if admins.is_empty() || has_admin(database).await? {
+        return Ok(0);
+    }
+
+    // Pin the promotion to the accounts holding the usernames now, not to whoever renames to them later
+    let filter = doc! {
+        "username": { "$in": admins },
+    };
+    let mut cursor = database.collections.account.find(filter).await?;
+    let mut ids = Vec::<ObjectId>::new();
+    while cursor.advance().await? {
+        ids.push(cursor.deserialize_current()?.id);
+    }
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let filter = doc! {
+        "_id": { "$in": ids },
+    };
+    let update = doc! {
+        "$set": { "role": bson::to_bson(&Role::Admin)? },
+    };
+    let update_result = database.collections.account.update_many(filter, update).await?;
+    Ok(update_result.modified_count)
+}
+
+async fn has_admin(database: &Database) -> Result<bool> {
+    let filter = doc! {
+        "role": bson::to_bson(&Role::Admin)?,
+    };
+    Ok(database.collections.account.count_documents(filter).limit(1).await? > 0)
+}
+
+/**
+ * Whether the account of [username] registers as the first admin: it is listed in "account.admins",
+ * and no admin exists yet, in which case it needs no invitation either.
+ **/
+pub(in crate::rest) async fn is_first_admin(config: &Config, database: &Database, username: &str) -> Result<bool> {
+    if !is_listed(config, username) {
+        return Ok(false);
+    }
+    Ok(!has_admin(database).await?)
+}
+
+/**
+ * Whether [username] is listed in "account.admins", which accounts cannot rename to.
+ **/
+pub(in crate::rest) fn is_listed(config: &Config, username: &str) -> bool {
+    config.admins().iter().any(|admin| admin == username)
+}
+
+/**
+ * Admin bootstrap config keys in [Config].
+ *
+ * Usernames of the admin accounts: "account.admins", comma-separated, none if not specified.
+ * While no admin exists, listed accounts are promoted to admins by [fairing], and a listed username
+ * registered becomes an admin at once, see [is_first_admin]. Renaming to a listed username is refused.
+ **/
+trait Bootstrap {
+    fn admins(&self) -> Vec<String>;
+}
+
+impl Bootstrap for Config {
+    fn admins(&self) -> Vec<String> {
+        self.get(str_vec!["account", "admins"])
+            .map(|admins| {
+                admins.split(',')
+                    .map(str::trim)
+                    .filter(|admin| !admin.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_admins() {
+        assert!(Config::of_pairs(&[]).admins().is_empty());
+        let config = Config::of_pairs(&[("account.admins", " root, ,ops_admin ")]);
+        assert_eq!(config.admins(), vec!["root".to_string(), "ops_admin".to_string()]);
+        assert!(is_listed(&config, "root"));
+        assert!(!is_listed(&config, "Root"));
+        assert!(!is_listed(&config, ""));
+    }
+}
@@ -1,16 +1,60 @@
 use rocket::Route;
 
-mod signature;
+pub(super) mod signature;
 
-mod onetime_password;
+pub(super) mod onetime_password;
+
+pub(super) mod recovery;
+
+mod password;
+
+mod multi_factor;
+
+mod magic_link;
+
+mod session;
+
+mod refresh;
+
+mod issuance;
 
 pub const MOUNT_POINT: &str = "/auth";
 
 pub fn routes() -> Vec<Route> {
     routes![
+        // GET /auth/sig/challenge
+        signature::challenge,
         // POST /auth/sig
         signature::verify,
         // POST /auth/otp
         onetime_password::verify,
+        // POST /auth/otp/enrollment
+        onetime_password::enrollment::enroll,
+        // POST /auth/otp/enrollment/confirmation
+        onetime_password::enrollment::confirm,
+        // DELETE /auth/otp
+        onetime_password::enrollment::disable,
+        // POST /auth/recovery
+        recovery::redeem,
+        // POST /auth/password
+        password::verify,
+        // PUT /auth/password
+        password::update,
+        // POST /auth/mfa
+        multi_factor::verify,
+        // PUT /auth/mfa
+        multi_factor::update,
+        // POST /auth/magic-link
+        magic_link::request,
+        // POST /auth/magic-link/redeem
+        magic_link::redeem,
+        // POST /auth/refresh
+        refresh::refresh,
+        // GET /auth/sessions
+        session::list,
+        // POST /auth/logout
+        session::logout,
+        // DELETE /auth/tokens/<id>
+        session::revoke,
     ]
 }
\ No newline at end of file
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use rocket::{http::Status, serde::json::Json};
+use serde::Serialize;
+
+use crate::state::{
+    database::collection::{
+        token::{Issuer, Refresh},
+        Token,
+    },
+    scope,
+    secret,
+    Client,
+    Database,
+    JsonWebToken,
+};
+
+/**
+ * Response of every login and refresh:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "access_token": "<JWT Token String>",
+ *     "refresh_token": "<Refresh Token String>",
+ *     "expiry": <Access-Token-Expiry-Timestamp-Seconds>,
+ *     "refresh_expiry": <Refresh-Token-Expiry-Timestamp-Milliseconds>
+ * }
+ * ```
+ *
+ * Breaking change: logins responded the bare JWT as `text/plain` before,
+ * clients now read it from [access_token] and keep [refresh_token] for `POST /auth/refresh`.
+ **/
+#[derive(Serialize)]
+pub struct IssuedToken {
+    access_token: String,
+    refresh_token: String,
+    expiry: i64,
+    refresh_expiry: i64,
+}
+
+/**
+ * Sign a JWT for [account_id] and record its [Token] with the [issuer] of the session,
+ * the requested [scope] and the device metadata of [client], starting a new token family.
+ * Unknown scopes are responded with 400 Bad Request, and disabled accounts with 403 Forbidden.
+ **/
+pub async fn issue(
+    database: &Database,
+    jsonwebtoken: &JsonWebToken,
+    client: &Client,
+    account_id: ObjectId,
+    issuer: Issuer,
+    scope: Option<Vec<String>>,
+) -> Result<Json<IssuedToken>, Status> {
+    let token_id = ObjectId::new();
+    issue_in_family(database, jsonwebtoken, client, token_id, account_id, issuer, scope, token_id).await
+}
+
+/**
+ * Same as [issue], but the token of [token_id] joins the existing token family of [family_id].
+ * The token is issued at the timestamp of [token_id].
+ **/
+#[allow(clippy::too_many_arguments)]
+pub async fn issue_in_family(
+    database: &Database,
+    jsonwebtoken: &JsonWebToken,
+    client: &Client,
+    token_id: ObjectId,
+    account_id: ObjectId,
+    issuer: Issuer,
+    scope: Option<Vec<String>>,
+    family_id: ObjectId,
+) -> Result<Json<IssuedToken>, Status> {
+    if scope.iter().flatten().any(|scope| !scope::is_known(scope)) {
+        return Err(Status::BadRequest);
+    }
+
+    // Disabled accounts can neither log in nor refresh
+    let account_filter = doc! {
+        "_id": account_id,
+        "disabled": { "$exists": false },
+    };
+    let account_count = database.collections.account.count_documents(account_filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if account_count == 0 {
+        return Err(Status::Forbidden);
+    }
+
+    let issue_timestamp = DateTime::from_timestamp_millis(token_id.timestamp().timestamp_millis())
+        .ok_or(Status::InternalServerError)?;
+    let claims = jsonwebtoken.new_claims(
+        &token_id.to_hex(), &account_id.to_hex(), &issue_timestamp, scope.as_deref(),
+    );
+    let jwt = jsonwebtoken.encode_jwt(&claims)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let refresh_token = secret::generate(secret::BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+    let refresh = Refresh {
+        hash: secret::hash(&refresh_token),
+        expiry: jsonwebtoken.refresh_expiry_from(&issue_timestamp).timestamp_millis(),
+    };
+    let refresh_expiry = refresh.expiry;
+
+    let mut token = Token::new(token_id, account_id, claims.expiry, issuer);
+    token.user_agent = client.user_agent.clone();
+    token.ip = client.ip.clone();
+    token.last_seen = Some(Utc::now().timestamp_millis());
+    token.family = Some(family_id);
+    token.refresh = Some(refresh);
+    token.scope = scope;
+    let inserted_id = database.collections.token.insert_one(&token)
+        .await
+        // Handle driver error
+        .map_err(|_| Status::InternalServerError)?
+        .inserted_id
+        .as_object_id()
+        // Handle object id conversion error
+        .ok_or(Status::InternalServerError)?;
+    // Make sure the inserted object id is the same as the token id
+    if inserted_id != token_id {
+        return Err(Status::InternalServerError);
+    }
+
+    Ok(Json(IssuedToken {
+        access_token: jwt,
+        refresh_token,
+        expiry: claims.expiry,
+        refresh_expiry,
+    }))
+}
@@ -0,0 +1,263 @@
+#![allow(private_interfaces)]
+use chrono::{Duration, Utc};
+use mongodb::bson::{doc, DateTime};
+use rocket::{http::Status, serde::json::Json};
+use serde::Deserialize;
+
+use crate::{
+    state::{
+        database::collection::{mail_token::Purpose, token::Issuer, MailToken},
+        scope,
+        secret,
+        Client,
+        Config,
+        ConfigState,
+        DatabaseState,
+        JsonWebTokenState,
+        MailerState,
+    },
+    str_vec,
+};
+
+use super::{
+    super::account::email::{is_cooling_down, with_token},
+    multi_factor::{self, Login},
+};
+
+#[derive(Deserialize)]
+struct MagicLinkRequest {
+    usr: String,
+    #[serde(default)]
+    scope: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct RedeemRequest {
+    token: String,
+}
+
+/**
+ * Email a single-use login link to the verified email address of the account.
+ *
+ * Request:
+ * ```text
+ * POST /auth/magic-link HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "usr": "<Username>",
+ *     "scope": ["<Scope>", ...]
+ * }
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 202 Accepted
+ * ```
+ *
+ * The link is "auth.magic-link.url" with the query "token=<Token>",
+ * for the client to submit the token to [redeem]. Only the latest link of an account is redeemable,
+ * and requests within "auth.magic-link.cooldown" of the latest link send nothing.
+ * Responded the same regardless of the existence of the account or its verified email address,
+ * except 503 Service Unavailable if mail is not configured.
+ **/
+#[post("/magic-link", data = "<json_request_body>")]
+pub(super) async fn request(
+    config: &ConfigState,
+    database: &DatabaseState,
+    mailer: &MailerState,
+    json_request_body: Json<MagicLinkRequest>,
+) -> Result<Status, Status> {
+    let mailer = mailer.as_ref()
+        .ok_or(Status::ServiceUnavailable)?;
+    let magic_link_request = json_request_body.into_inner();
+    if magic_link_request.scope.iter().flatten().any(|scope| !scope::is_known(scope)) {
+        return Err(Status::BadRequest);
+    }
+
+    let account = database.find_account_by_username(&magic_link_request.usr)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    let Some(account) = account.filter(|account| account.is_enabled()) else {
+        return Ok(Status::Accepted);
+    };
+    let Some(email) = account.email.clone().filter(|_| account.email_verified.is_some()) else {
+        return Ok(Status::Accepted);
+    };
+
+    let now_timestamp = Utc::now();
+
+    if is_cooling_down(database, account.id, "MagicLink", config.magic_link_cooldown_millis()).await? {
+        return Ok(Status::Accepted);
+    }
+
+    // Invalidate the links sent before
+    let filter = doc! {
+        "account": account.id,
+        "purpose.MagicLink": { "$exists": true },
+    };
+    database.collections.mail_token.delete_many(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let token = secret::generate(secret::BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let expiry = now_timestamp + Duration::milliseconds(config.magic_link_timeout_millis());
+    let purpose = Purpose::MagicLink { scope: magic_link_request.scope };
+    let mail_token = MailToken::new(
+        account.id, secret::hash(&token), purpose, DateTime::from_millis(expiry.timestamp_millis()),
+    );
+    database.collections.mail_token.insert_one(&mail_token)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let link = with_token(&config.magic_link_url(), &token);
+    let body = format!(
+        "Hi {},\n\nOpen the link below to log in:\n{}\n\nThe link expires at {}. \
+        If you did not request it, you can ignore this email.\n",
+        account.username, link, expiry.to_rfc2822(),
+    );
+    // Sent in the background, so that neither the response nor its time tells anything about the account
+    mailer.send_in_background(email, "Your login link", body);
+
+    Ok(Status::Accepted)
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /auth/magic-link/redeem HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "token": "<Token>"
+ * }
+ * ```
+ * The scope is the one requested along with the link.
+ *
+ * Successful Response: [Login]
+ *
+ * Every link is redeemable once only.
+ * All errors are responded with HTTP status codes only.
+ **/
+#[post("/magic-link/redeem", data = "<json_request_body>")]
+pub(super) async fn redeem(
+    config: &ConfigState,
+    database: &DatabaseState,
+    jsonwebtoken: &JsonWebTokenState,
+    client: Client,
+    json_request_body: Json<RedeemRequest>,
+) -> Result<Json<Login>, Status> {
+    let redeem_request = json_request_body.into_inner();
+
+    let filter = doc! {
+        "hash": secret::hash(&redeem_request.token),
+        "purpose.MagicLink": { "$exists": true },
+        "expiry": { "$gte": DateTime::now() },
+    };
+    let mail_token = database.collections.mail_token.find_one_and_delete(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+    let Purpose::MagicLink { scope } = mail_token.purpose else {
+        return Err(Status::Unauthorized);
+    };
+
+    let filter = doc! {
+        "_id": mail_token.account,
+    };
+    let account = database.collections.account.find_one(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+
+    multi_factor::login(
+        config,
+        database,
+        jsonwebtoken,
+        &client,
+        &account,
+        Issuer::MagicLink,
+        scope,
+    ).await
+}
+
+// 15 minutes
+const MAGIC_LINK_TIMEOUT: i64 = 15 * 60 * 1000;
+
+// 1 minute
+const MAGIC_LINK_COOLDOWN: i64 = 60 * 1000;
+
+const MAGIC_LINK_URL: &str = "http://localhost:8000/magic-link";
+
+/**
+ * Magic link config keys in [Config].
+ *
+ * Validity of a link in milliseconds: "auth.magic-link.timeout",
+ * set as [MAGIC_LINK_TIMEOUT] if not specified.
+ *
+ * Minimum delay in milliseconds between the links sent to an account: "auth.magic-link.cooldown",
+ * set as [MAGIC_LINK_COOLDOWN] if not specified.
+ *
+ * Page of the client opening the link: "auth.magic-link.url",
+ * set as [MAGIC_LINK_URL] if not specified.
+ **/
+trait MagicLink {
+    fn magic_link_timeout_millis(&self) -> i64;
+    fn magic_link_cooldown_millis(&self) -> i64;
+    fn magic_link_url(&self) -> String;
+}
+
+impl MagicLink for Config {
+    fn magic_link_timeout_millis(&self) -> i64 {
+        self.get(str_vec!["auth", "magic-link", "timeout"])
+            .and_then(|timeout| timeout.parse::<i64>().ok())
+            .filter(|timeout| *timeout > 0)
+            .unwrap_or(MAGIC_LINK_TIMEOUT)
+    }
+
+    fn magic_link_cooldown_millis(&self) -> i64 {
+        self.get(str_vec!["auth", "magic-link", "cooldown"])
+            .and_then(|cooldown| cooldown.parse::<i64>().ok())
+            .filter(|cooldown| *cooldown >= 0)
+            .unwrap_or(MAGIC_LINK_COOLDOWN)
+    }
+
+    fn magic_link_url(&self) -> String {
+        self.get(str_vec!["auth", "magic-link", "url"])
+            .cloned()
+            .unwrap_or(MAGIC_LINK_URL.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_magic_link_config() {
+        let config = Config::of_pairs(&[]);
+        assert_eq!(config.magic_link_timeout_millis(), MAGIC_LINK_TIMEOUT);
+        assert_eq!(config.magic_link_cooldown_millis(), MAGIC_LINK_COOLDOWN);
+        assert_eq!(config.magic_link_url(), MAGIC_LINK_URL);
+
+        let config = Config::of_pairs(&[
+            ("auth.magic-link.timeout", "0"),
+            ("auth.magic-link.cooldown", "-1"),
+        ]);
+        assert_eq!(config.magic_link_timeout_millis(), MAGIC_LINK_TIMEOUT);
+        assert_eq!(config.magic_link_cooldown_millis(), MAGIC_LINK_COOLDOWN);
+
+        let config = Config::of_pairs(&[
+            ("auth.magic-link.timeout", "60000"),
+            ("auth.magic-link.cooldown", "0"),
+            ("auth.magic-link.url", "https://example.com/login"),
+        ]);
+        assert_eq!(config.magic_link_timeout_millis(), 60000);
+        assert_eq!(config.magic_link_cooldown_millis(), 0);
+        assert_eq!(with_token(&config.magic_link_url(), "abc"), "https://example.com/login?token=abc");
+    }
+}
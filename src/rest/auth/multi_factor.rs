@@ -0,0 +1,422 @@
+#![allow(private_interfaces)]
+use chrono::{Duration, Utc};
+use mongodb::bson::{self, doc, oid::ObjectId, DateTime, Document};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::{
+        database::collection::{
+            account::OnetimePasswordSecret,
+            token::{Issuer, State},
+            Account,
+            MfaTicket,
+        },
+        scope::AccountWrite,
+        secret,
+        Authorization,
+        Client,
+        Config,
+        ConfigState,
+        Database,
+        DatabaseState,
+        JsonWebToken,
+        JsonWebTokenState,
+        RequireScope,
+    },
+    str_vec,
+};
+
+use super::{
+    issuance::{self, IssuedToken},
+    onetime_password,
+    password::Password,
+    recovery,
+};
+
+/**
+ * Response of a login by a first factor, either the token, or a ticket to be completed by [verify]
+ * if the account requires multi-factor authentication:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "mfa_ticket": "<MFA-Ticket>",
+ *     "expiry": <Ticket-Expiry-Timestamp-Milliseconds>
+ * }
+ * ```
+ **/
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Login {
+    Issued(IssuedToken),
+    Pending {
+        mfa_ticket: String,
+        expiry: i64,
+    },
+}
+
+#[derive(Deserialize)]
+struct MultiFactorRequest {
+    mfa_ticket: String,
+    #[serde(default)]
+    otp: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateRequest {
+    required: bool,
+    #[serde(default)]
+    otp: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+/**
+ * Issue the token of a login by the first factor [issuer],
+ * or a ticket for the second factor if required, see [is_mfa_required].
+ **/
+pub async fn login(
+    config: &Config,
+    database: &Database,
+    jsonwebtoken: &JsonWebToken,
+    client: &Client,
+    account: &Account,
+    issuer: Issuer,
+    scope: Option<Vec<String>>,
+) -> Result<Json<Login>, Status> {
+    if !is_mfa_required(config, account, &issuer) {
+        let Json(issued_token) = issuance::issue(database, jsonwebtoken, client, account.id, issuer, scope).await?;
+        return Ok(Json(Login::Issued(issued_token)));
+    }
+    // The second factor is a one-time password, or a recovery code in place of it
+    if !account.onetime_password_secret.as_ref()
+        .is_some_and(OnetimePasswordSecret::is_confirmed) {
+        return Err(Status::Forbidden);
+    }
+
+    let now_timestamp = Utc::now();
+
+    let ticket = secret::generate(secret::BYTES)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let expiry = (now_timestamp + Duration::milliseconds(config.mfa_timeout_millis())).timestamp_millis();
+    let mfa_ticket = MfaTicket::new(account.id, secret::hash(&ticket), issuer, scope, DateTime::from_millis(expiry));
+    database.collections.mfa_ticket.insert_one(&mfa_ticket)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(Login::Pending {
+        mfa_ticket: ticket,
+        expiry,
+    }))
+}
+
+/**
+ * Complete a pending login with the second factor.
+ *
+ * Request:
+ * ```text
+ * POST /auth/mfa HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "mfa_ticket": "<MFA-Ticket>",
+ *     "otp": "<One-time Password>"
+ * }
+ * ```
+ * Where [otp] can be replaced by ["code": "<Recovery-Code>"].
+ * The scope is the one requested with the first factor.
+ *
+ * Successful Response: [IssuedToken]
+ *
+ * Every ticket has a single attempt only.
+ * All errors are responded with HTTP status codes only.
+ **/
+#[post("/mfa", data = "<json_request_body>")]
+pub(super) async fn verify(
+    config: &ConfigState,
+    database: &DatabaseState,
+    jsonwebtoken: &JsonWebTokenState,
+    client: Client,
+    json_request_body: Json<MultiFactorRequest>,
+) -> Result<Json<IssuedToken>, Status> {
+    let multi_factor_request = json_request_body.into_inner();
+
+    // Consume the ticket before verifying, so that the second factor cannot be guessed repeatedly
+    let filter = doc! {
+        "hash": secret::hash(&multi_factor_request.mfa_ticket),
+        "expiry": { "$gte": DateTime::now() },
+    };
+    let mfa_ticket = database.collections.mfa_ticket.find_one_and_delete(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+
+    let filter = doc! {
+        "_id": mfa_ticket.account,
+    };
+    let account = database.collections.account.find_one(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+
+    let second_factor = match (multi_factor_request.otp, multi_factor_request.code) {
+        (Some(otp), _) => {
+            onetime_password::consume(config, database, &account, &otp).await?;
+            Issuer::OnetimePassword
+        }
+        (None, Some(code)) => {
+            recovery::consume(database, &account, &code).await?;
+            Issuer::RecoveryCode
+        }
+        (None, None) => return Err(Status::BadRequest),
+    };
+
+    let issuer = Issuer::MultiFactor {
+        first: Box::new(mfa_ticket.issuer),
+        second: Box::new(second_factor),
+    };
+    issuance::issue(database, jsonwebtoken, &client, account.id, issuer, mfa_ticket.scope).await
+}
+
+/**
+ * Require or stop requiring multi-factor authentication for the logins of the authorized account.
+ *
+ * Request:
+ * ```text
+ * PUT /auth/mfa HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "required": <true|false>,
+ *     "otp": "<One-time Password>"
+ * }
+ * ```
+ * Where [otp], or ["code": "<Recovery-Code>"] in place of it, is required to stop requiring,
+ * along with a session logged in recently.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Responded with 409 Conflict if required without a confirmed TOTP enrollment,
+ * and 403 Forbidden if stopping to require by a personal access token or a session logged in long ago.
+ * Requiring it revokes the sessions logged in by a single factor, except the authorizing one,
+ * and all personal access tokens, which bypass the second factor.
+ **/
+#[put("/mfa", data = "<json_request_body>")]
+pub(super) async fn update(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<UpdateRequest>,
+) -> Result<Status, Status> {
+    let update_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    if !update_request.required && account.mfa_required {
+        if !may_stop_requiring(&authorization) {
+            return Err(Status::Forbidden);
+        }
+        match (update_request.otp, update_request.code) {
+            (Some(otp), _) => onetime_password::consume(config, database, account, &otp).await?,
+            (None, Some(code)) => recovery::consume(database, account, &code).await?,
+            (None, None) => return Err(Status::BadRequest),
+        }
+    }
+
+    let filter = if update_request.required {
+        doc! {
+            "_id": authorization.account.id,
+            "onetime_password_secret.issue": { "$exists": true },
+        }
+    } else {
+        doc! {
+            "_id": authorization.account.id,
+        }
+    };
+    let update = doc! {
+        "$set": { "mfa_required": update_request.required },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Conflict);
+    }
+    if !update_request.required {
+        return Ok(Status::NoContent);
+    }
+
+    let state = bson::to_bson(&State::Disabled(Utc::now().timestamp_millis()))
+        .map_err(|_| Status::InternalServerError)?;
+    let update = doc! {
+        "$set": { "state": state },
+    };
+    let filter = single_factor_filter(authorization.account.id, authorization.session().map(|token| token.id));
+    database.collections.token.update_many(filter, update.clone())
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    // Normal state is not serialized
+    let filter = doc! {
+        "account": authorization.account.id,
+        "state": { "$exists": false },
+    };
+    database.collections.access_token.update_many(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(Status::NoContent)
+}
+
+/**
+ * Whether [authorization] can stop requiring multi-factor authentication, a session logged in recently only,
+ * along with the second factor.
+ **/
+fn may_stop_requiring(authorization: &Authorization) -> bool {
+    authorization.is_recent_login()
+}
+
+/**
+ * Whether a login by [issuer] needs a second factor:
+ * the account requires it, or "auth.password.require-otp" does for the password.
+ **/
+fn is_mfa_required(config: &Config, account: &Account, issuer: &Issuer) -> bool {
+    account.mfa_required || matches!(issuer, Issuer::Password) && config.password_requires_otp()
+}
+
+/**
+ * Filter of the active sessions of the account logged in by a single factor, except [kept].
+ **/
+fn single_factor_filter(account_id: ObjectId, kept: Option<ObjectId>) -> Document {
+    // Normal state is not serialized
+    let mut filter = doc! {
+        "account": account_id,
+        "state": { "$exists": false },
+        "issuer.MultiFactor": { "$exists": false },
+    };
+    if let Some(token_id) = kept {
+        filter.insert("_id", doc! { "$ne": token_id });
+    }
+    filter
+}
+
+// 5 minutes
+const MFA_TIMEOUT: i64 = 5 * 60 * 1000;
+
+trait MultiFactor {
+    fn mfa_timeout_millis(&self) -> i64;
+}
+
+impl MultiFactor for Config {
+    /**
+     * Validity of a pending login in milliseconds: "auth.mfa.timeout",
+     * set as [MFA_TIMEOUT] if not specified.
+     **/
+    fn mfa_timeout_millis(&self) -> i64 {
+        self.get(str_vec!["auth", "mfa", "timeout"])
+            .and_then(|timeout| timeout.parse::<i64>().ok())
+            .filter(|timeout| *timeout > 0)
+            .unwrap_or(MFA_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rocket::serde::json::{json, to_value};
+
+    use crate::state::{
+        authorization::Credential,
+        database::collection::{AccessToken, Token},
+    };
+
+    use super::*;
+
+    fn account(mfa_required: bool) -> Account {
+        let mut account = Account::new(ObjectId::new(), "user".into(), Vec::new(), None, Vec::new());
+        account.mfa_required = mfa_required;
+        account
+    }
+
+    #[test]
+    fn test_is_mfa_required() {
+        let config = Config::of_pairs(&[]);
+        assert!(!is_mfa_required(&config, &account(false), &Issuer::Password));
+        assert!(is_mfa_required(&config, &account(true), &Issuer::Password));
+        assert!(is_mfa_required(&config, &account(true), &Issuer::MagicLink));
+
+        // The override applies to the password only
+        let config = Config::of_pairs(&[("auth.password.require-otp", "true")]);
+        assert!(is_mfa_required(&config, &account(false), &Issuer::Password));
+        assert!(!is_mfa_required(&config, &account(false), &Issuer::MagicLink));
+        assert!(!is_mfa_required(&config, &account(false), &Issuer::PublicKey(ObjectId::new())));
+    }
+
+    #[test]
+    fn test_may_stop_requiring() {
+        // Logged in now
+        let token = Token::new(ObjectId::new(), ObjectId::new(), 0, Issuer::Password);
+        let authorization = Authorization { credential: Credential::Session(token), account: account(true) };
+        assert!(may_stop_requiring(&authorization));
+
+        // Logged in an hour ago
+        let login_id = ObjectId::from_parts((Utc::now().timestamp() - 60 * 60) as u32, [0; 5], [0; 3]);
+        let token = Token::new(login_id, ObjectId::new(), 0, Issuer::Password);
+        let authorization = Authorization { credential: Credential::Session(token), account: account(true) };
+        assert!(!may_stop_requiring(&authorization));
+
+        let access_token = AccessToken::new(ObjectId::new(), "CI".into(), "<Hash>".into(), None, None);
+        let authorization = Authorization { credential: Credential::AccessToken(access_token), account: account(true) };
+        assert!(!may_stop_requiring(&authorization));
+    }
+
+    #[test]
+    fn test_single_factor_filter() {
+        let account_id = ObjectId::new();
+        let filter = single_factor_filter(account_id, None);
+        assert_eq!(filter.get_object_id("account").unwrap(), account_id);
+        assert!(!filter.contains_key("_id"));
+
+        let token_id = ObjectId::new();
+        let filter = single_factor_filter(account_id, Some(token_id));
+        assert_eq!(filter.get_document("_id").unwrap(), &doc! { "$ne": token_id });
+
+        // Sessions completed by a second factor are told apart by the key of their issuer
+        let issuer = Issuer::MultiFactor {
+            first: Box::new(Issuer::Password),
+            second: Box::new(Issuer::OnetimePassword),
+        };
+        let token = bson::to_document(&Token::new(ObjectId::new(), account_id, 0, issuer)).unwrap();
+        assert!(token.get_document("issuer").unwrap().contains_key("MultiFactor"));
+        let token = bson::to_document(&Token::new(ObjectId::new(), account_id, 0, Issuer::Password)).unwrap();
+        assert!(token.get_document("issuer").is_err());
+    }
+
+    #[test]
+    fn test_pending_login() {
+        let login = Login::Pending {
+            mfa_ticket: "ticket".into(),
+            expiry: 1_700_000_000_000,
+        };
+        let json = to_value(&login).unwrap();
+        assert_eq!(json, json!({ "mfa_ticket": "ticket", "expiry": 1_700_000_000_000_i64 }));
+    }
+
+    #[test]
+    fn test_mfa_timeout() {
+        assert_eq!(Config::of_pairs(&[]).mfa_timeout_millis(), MFA_TIMEOUT);
+        let config = Config::of_pairs(&[("auth.mfa.timeout", "0")]);
+        assert_eq!(config.mfa_timeout_millis(), MFA_TIMEOUT);
+        let config = Config::of_pairs(&[("auth.mfa.timeout", "60000")]);
+        assert_eq!(config.mfa_timeout_millis(), 60000);
+    }
+}
@@ -1,23 +1,36 @@
 #![allow(private_interfaces)]
-use chrono::DateTime;
-use mongodb::bson::oid::ObjectId;
+use chrono::Utc;
+use mongodb::bson::doc;
 use openssl::hash::MessageDigest;
 use rocket::{
+    fairing::AdHoc,
     http::Status,
     serde::json::Json,
 };
 use serde::Deserialize;
 
 use crate::state::{
-    database::collection::Token,
-    Config, ConfigState, DatabaseState, JsonWebTokenState
+    database::collection::{account::OnetimePasswordSecret, token::Issuer, Account},
+    Client, Config, ConfigState, Database, DatabaseState, JsonWebTokenState
 };
 use crate::str_vec;
 
+use super::{
+    issuance::{self, IssuedToken},
+    recovery,
+};
+
+mod totp;
+use totp::{Secret, Totp};
+
+pub(super) mod enrollment;
+
 #[derive(Deserialize)]
 struct VerifyOtpRequest {
     pub usr: String,
     pub otp: String,
+    #[serde(default)]
+    pub scope: Option<Vec<String>>,
 }
 
 #[post("/otp", data = "<json_request_body>")]
@@ -25,86 +38,211 @@ pub(super) async fn verify(
     config: &ConfigState,
     database: &DatabaseState,
     jsonwebtoken: &JsonWebTokenState,
+    client: Client,
     json_request_body: Json<VerifyOtpRequest>,
-) -> Result<String, Status> {
+) -> Result<Json<IssuedToken>, Status> {
     let verify_otp_request = json_request_body.into_inner();
 
-    let filter = account_filter::from_username(&verify_otp_request.usr)
-        .map_err(|_| Status::InternalServerError)?;
-    let account = database.collections.account.find_one(filter)
+    let account = database.find_account_by_username(&verify_otp_request.usr)
         .await
         .map_err(|_| Status::InternalServerError)?
         .ok_or(Status::Forbidden)?;
-    let otp_secret = account.onetime_password_secret
+    // One-time password is the second factor of accounts requiring multi-factor authentication
+    if account.mfa_required {
+        return Err(Status::Forbidden);
+    }
+    // Pending enrollment cannot be used for login
+    if !account.onetime_password_secret.as_ref()
+        .is_some_and(OnetimePasswordSecret::is_confirmed) {
+        return Err(Status::Forbidden);
+    }
+
+    consume(config, database, &account, &verify_otp_request.otp).await?;
+
+    issuance::issue(
+        database, jsonwebtoken, &client, account.id, Issuer::OnetimePassword, verify_otp_request.scope,
+    ).await
+}
+
+/**
+ * Verify [otp] against the secret of [account] within the configured window,
+ * then record its time step, so that the same or an earlier code cannot be accepted again.
+ **/
+pub(super) async fn consume(
+    config: &Config,
+    database: &Database,
+    account: &Account,
+    otp: &str,
+) -> Result<(), Status> {
+    let otp_secret = account.onetime_password_secret.as_ref()
         .ok_or(Status::Forbidden)?;
 
-    let message_digest = config.hashing_algorithm();
-    let hash = otp::hash(&otp_secret.secret, message_digest)
+    let (totp, secret) = match totp::decode_stored_secret(&otp_secret.secret) {
+        Some(Secret::Base32(secret)) => (config.totp(), secret),
+        Some(Secret::Legacy(secret)) => (Totp::legacy(config.legacy_hashing_algorithm()), secret),
+        None => return Err(Status::InternalServerError),
+    };
+    let time_step = totp
+        .verify(&secret, otp, &Utc::now(), config.window())
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+    let time_step = i64::try_from(time_step)
         .map_err(|_| Status::InternalServerError)?;
-    let otp = otp::generate(hash)
-        .ok_or(Status::InternalServerError)?;
-    if otp != verify_otp_request.otp {
+    if otp_secret.last_step.is_some_and(|last_step| time_step <= last_step) {
         return Err(Status::Unauthorized);
     }
 
-    let object_id = ObjectId::new();
-    let timestamp = DateTime::from_timestamp(object_id.timestamp().timestamp_millis(), 0)
-        .ok_or(Status::InternalServerError)?;
-    let claims = jsonwebtoken.new_claims(&object_id.to_hex(), &account.id.to_hex(), &timestamp);
-    let token = Token::of_passkey(object_id, account.id, claims.expiry);
-    let inserted_id = database.collections.token.insert_one(&token)
+    // Conditional update, a concurrent request with the same code will no longer match
+    let filter = doc! {
+        "_id": account.id,
+        "onetime_password_secret.secret": &otp_secret.secret,
+        "$or": [
+            { "onetime_password_secret.last_step": { "$exists": false } },
+            { "onetime_password_secret.last_step": { "$lt": time_step } },
+        ],
+    };
+    let update = doc! {
+        "$set": { "onetime_password_secret.last_step": time_step },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
         .await
-        .map_err(|_| Status::InternalServerError)?
-        .inserted_id
-        .as_object_id()
-        .ok_or(Status::InternalServerError)?;
-    if inserted_id.to_hex() != object_id.to_hex() {
-        return Err(Status::InternalServerError);
-    }
-    let jwt = jsonwebtoken.encode_jwt(&claims)
         .map_err(|_| Status::InternalServerError)?;
-    Ok(jwt)
+    if update_result.modified_count == 0 {
+        return Err(Status::Unauthorized);
+    }
+    Ok(())
 }
 
-mod account_filter {
-    use mongodb::{
-        bson,
-        bson::{
-            ser::Result,
-            Document,
+/**
+ * Confirm the pending TOTP secret of [account] with a first valid [otp],
+ * returning a new set of recovery codes, which replaces any previous ones.
+ **/
+pub(in crate::rest) async fn confirm(
+    config: &Config,
+    database: &Database,
+    account: &Account,
+    otp: &str,
+) -> Result<Vec<String>, Status> {
+    let otp_secret = account.onetime_password_secret.as_ref()
+        .ok_or(Status::NotFound)?;
+    if otp_secret.is_confirmed() {
+        return Err(Status::Conflict);
+    }
+
+    consume(config, database, account, otp).await?;
+
+    let (recovery_codes, recovery_code_hashes) = recovery::generate(config)
+        .map_err(|_| Status::InternalServerError)?;
+
+    let filter = doc! {
+        "_id": account.id,
+        "onetime_password_secret.secret": &otp_secret.secret,
+    };
+    let update = doc! {
+        "$set": {
+            "onetime_password_secret.issue": Utc::now().timestamp_millis(),
+            "recovery_codes": recovery_code_hashes,
         },
+        "$unset": { "onetime_password_secret.registration": "" },
     };
-    use serde::Serialize;
-
-    #[derive(Serialize)]
-    struct Filter {
-        pub username: String,
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        // Secret is replaced by another enrollment in the meantime
+        return Err(Status::Conflict);
     }
 
-    pub fn from_username(username: &String) -> Result<Document> {
-        let filter = Filter { username: username.clone() };
-        bson::to_document(&filter)
-    }
+    Ok(recovery_codes)
+}
 
+/**
+ * Generate a new TOTP secret for [username], returned in base32 along with its provisioning URI.
+ **/
+pub(in crate::rest) fn generate_secret(config: &Config, username: &str) -> Result<(String, String), Status> {
+    let totp = config.totp();
+    let secret = totp.generate_secret()
+        .map_err(|_| Status::InternalServerError)?;
+    let uri = totp.provisioning_uri(&config.issuer(), username, &secret);
+    Ok((totp::encode_secret(&secret), uri))
 }
 
+/**
+ * Check the TOTP config on the ignition of Rocket, so that an unknown hashing algorithm
+ * panics at startup rather than on the first enrollment.
+ **/
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("OTP Config", |rocket| Box::pin(async move {
+        rocket.state::<Config>().unwrap().hashing_algorithm();
+        rocket
+    }))
+}
+
+/**
+ * TOTP config keys in [Config].
+ *
+ * HMAC hashing algorithm: "auth.otp.hash-alg", one of "SHA1", "SHA256" or "SHA512",
+ * set as [OTP_HASHING_ALGORITHM] if not specified, panicking on any other.
+ * The default is "SHA1", which authenticator apps assume, instead of the "SHA256" before,
+ * kept by [OnetimePassword::legacy_hashing_algorithm] for the secrets enrolled before.
+ *
+ * Time step in seconds: "auth.otp.period", set as [OTP_PERIOD] if not specified.
+ *
+ * Number of digits: "auth.otp.digits", from 6 to 8, set as [OTP_DIGITS] if not specified.
+ *
+ * Accepted clock skew: "auth.otp.window", in time steps before and after the current one,
+ * capped at [OTP_MAX_WINDOW], set as [OTP_WINDOW] if not specified.
+ *
+ * Issuer label shown in authenticator apps: "auth.otp.issuer", set as [OTP_ISSUER] if not specified.
+ **/
 trait OnetimePassword {
     fn hashing_algorithm(&self) -> MessageDigest;
+    fn legacy_hashing_algorithm(&self) -> MessageDigest;
+    fn period(&self) -> i64;
+    fn digits(&self) -> u32;
+    fn window(&self) -> u64;
+    fn issuer(&self) -> String;
+    fn totp(&self) -> Totp {
+        Totp::new(self.hashing_algorithm(), self.period(), self.digits())
+    }
 }
 
-const OTP_HASHING_ALGORITHM: &str = "SHA256";
+const OTP_HASHING_ALGORITHM: &str = "SHA1";
+const LEGACY_OTP_HASHING_ALGORITHM: &str = "SHA256";
+const OTP_PERIOD: i64 = 30/*seconds*/;
+const OTP_DIGITS: u32 = 6;
+const OTP_WINDOW: u64 = 1;
+const OTP_MAX_WINDOW: u64 = 10;
+const OTP_ISSUER: &str = "Cloudy";
 impl OnetimePassword for Config {
     fn hashing_algorithm(&self) -> MessageDigest {
         let algorithm = self.get(str_vec!["auth", "otp", "hash-alg"])
             .map(|algorithm| algorithm.to_uppercase())
             .unwrap_or(OTP_HASHING_ALGORITHM.into());
         match algorithm.as_str() {
+            "SHA1" => MessageDigest::sha1(),
+            "SHA256" => MessageDigest::sha256(),
+            "SHA512" => MessageDigest::sha512(),
+            algorithm => panic!(r#"Panic: Unknown OTP hashing algorithm "{algorithm}"."#),
+        }
+    }
+
+    /**
+     * Digest of the legacy secrets, the same "auth.otp.hash-alg" as before,
+     * set as [LEGACY_OTP_HASHING_ALGORITHM] if not specified.
+     **/
+    fn legacy_hashing_algorithm(&self) -> MessageDigest {
+        let algorithm = self.get(str_vec!["auth", "otp", "hash-alg"])
+            .map(|algorithm| algorithm.to_uppercase())
+            .unwrap_or(LEGACY_OTP_HASHING_ALGORITHM.into());
+        match algorithm.as_str() {
+            /* MD5 */
             "MD5" => MessageDigest::md5(),
 
             /* SHA-1 */
             "SHA1" => MessageDigest::sha1(),
 
-            /* SHA-3 Family */
+            /* SHA-2 Family */
             "SHA224" => MessageDigest::sha224(),
             "SHA384" => MessageDigest::sha384(),
             "SHA512" => MessageDigest::sha512(),
@@ -115,70 +253,59 @@ impl OnetimePassword for Config {
             "SHA3-384" => MessageDigest::sha3_384(),
             "SHA3-512" => MessageDigest::sha3_512(),
 
-            OTP_HASHING_ALGORITHM | _ => MessageDigest::sha256(),
+            _ => MessageDigest::sha256(),
         }
     }
-}
-
-mod otp {
-    use chrono::Utc;
-    use openssl::{
-        error::ErrorStack,
-        hash::{DigestBytes, Hasher, MessageDigest},
-        base64,
-    };
-
-    const TIMEOUT: i64 = 30/*seconds*/;
-    const DIGITS: u32 = 6;
-
-    pub fn hash(
-        secret: &String,
-        message_digest: MessageDigest,
-    ) -> Result<DigestBytes, ErrorStack> {
-        let secret_bytes = base64::decode_block(&*secret)?;
-        let time_counter = (Utc::now().timestamp() / TIMEOUT).to_be_bytes();
-
-        let mut hasher = Hasher::new(message_digest)?;
-        hasher.update(&secret_bytes)?;
-        hasher.update(&time_counter)?;
 
-        hasher.finish()
+    fn period(&self) -> i64 {
+        self.get(str_vec!["auth", "otp", "period"])
+            .and_then(|period| period.parse::<i64>().ok())
+            .filter(|period| *period > 0)
+            .unwrap_or(OTP_PERIOD)
     }
 
-    pub fn generate(
-        digest_bytes: DigestBytes,
-    ) -> Option<String> {
-        let Some(offset) = digest_bytes.last().map(|byte| (byte & 0xF) as usize) else {
-            return None;
-        };
-
-        let binary_code = extract_information(offset).iter()
-            .map(|information| digest_bytes.extract(*information))
-            .fold(0, |acc, val| acc | val);
+    fn digits(&self) -> u32 {
+        self.get(str_vec!["auth", "otp", "digits"])
+            .and_then(|digits| digits.parse::<u32>().ok())
+            .filter(|digits| (6..=8).contains(digits))
+            .unwrap_or(OTP_DIGITS)
+    }
 
-        let totp = binary_code % 10_u32.pow(DIGITS);
-        let totp = format!("{:0digits$}", totp, digits = DIGITS as usize);
-        Some(totp)
+    fn window(&self) -> u64 {
+        self.get(str_vec!["auth", "otp", "window"])
+            .and_then(|window| window.parse::<u64>().ok())
+            .map(|window| window.min(OTP_MAX_WINDOW))
+            .unwrap_or(OTP_WINDOW)
     }
 
-    fn extract_information(offset: usize) -> Vec<(usize, u32, u32)> {
-        vec![
-            (offset, 0x7F, 24),
-            (offset + 1, 0xFF, 16),
-            (offset + 2, 0xFF, 8),
-            (offset + 3, 0xFF, 0)
-        ]
+    fn issuer(&self) -> String {
+        self.get(str_vec!["auth", "otp", "issuer"])
+            .cloned()
+            .unwrap_or(OTP_ISSUER.into())
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    trait Hashing {
-        fn extract(&self, information: (usize, u32, u32)) -> u32;
+    #[test]
+    fn test_hashing_algorithm() {
+        assert!(Config::of_pairs(&[]).hashing_algorithm() == MessageDigest::sha1());
+        assert!(Config::of_pairs(&[("auth.otp.hash-alg", "sha512")]).hashing_algorithm() == MessageDigest::sha512());
+        assert!(Config::of_pairs(&[]).legacy_hashing_algorithm() == MessageDigest::sha256());
     }
 
-    impl Hashing for DigestBytes {
-        fn extract(&self, information: (usize, u32, u32)) -> u32 {
-            let (index, mask, shifting) = information;
-            (self[index] as u32 & mask) << shifting
-        }
+    #[test]
+    #[should_panic]
+    fn test_unknown_hashing_algorithm() {
+        Config::of_pairs(&[("auth.otp.hash-alg", "SHA384")]).hashing_algorithm();
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_window() {
+        assert_eq!(Config::of_pairs(&[]).window(), OTP_WINDOW);
+        assert_eq!(Config::of_pairs(&[("auth.otp.window", "3")]).window(), 3);
+        assert_eq!(Config::of_pairs(&[("auth.otp.window", "1000000")]).window(), OTP_MAX_WINDOW);
+    }
+}
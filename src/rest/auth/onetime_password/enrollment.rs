@@ -0,0 +1,235 @@
+#![allow(private_interfaces)]
+use mongodb::{bson, bson::doc};
+use qrcode::{render::svg, QrCode};
+use rocket::{http::Status, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    database::collection::account::OnetimePasswordSecret,
+    scope::AccountWrite,
+    ConfigState,
+    DatabaseState,
+    RequireScope,
+};
+
+use super::{super::recovery, totp, OnetimePassword};
+
+#[derive(Serialize)]
+struct EnrollmentResponse {
+    secret: String,
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qr: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfirmationRequest {
+    otp: String,
+}
+
+#[derive(Serialize)]
+struct ConfirmationResponse {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct DisableRequest {
+    #[serde(default)]
+    otp: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+// Minimum width and height of the QR code SVG, in pixels
+const QR_DIMENSION: u32 = 200;
+
+/**
+ * Generate a new pending TOTP secret for the authorized account,
+ * replacing any previous pending one.
+ *
+ * Request:
+ * ```text
+ * POST /auth/otp/enrollment[?qr=true] HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "secret": "<Base32-Secret>",
+ *     "uri": "otpauth://totp/<Issuer>:<Username>?secret=<Base32-Secret>&...",
+ *     "qr": "<SVG of the URI, only if requested>"
+ * }
+ * ```
+ *
+ * Responded with 409 Conflict if the enrollment has already been confirmed.
+ **/
+#[post("/otp/enrollment?<qr>")]
+pub async fn enroll(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    qr: Option<bool>,
+) -> Result<Json<EnrollmentResponse>, Status> {
+    let account = authorization.into_inner().account;
+    if account.onetime_password_secret.as_ref()
+        .is_some_and(OnetimePasswordSecret::is_confirmed) {
+        return Err(Status::Conflict);
+    }
+
+    let totp = config.totp();
+    let secret = totp.generate_secret()
+        .map_err(|_| Status::InternalServerError)?;
+    let onetime_password_secret = OnetimePasswordSecret::pending(totp::encode_secret(&secret));
+    let onetime_password_secret = bson::to_bson(&onetime_password_secret)
+        .map_err(|_| Status::InternalServerError)?;
+
+    // Never overwrite a secret confirmed in the meantime
+    let filter = doc! {
+        "_id": account.id,
+        "onetime_password_secret.issue": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "onetime_password_secret": onetime_password_secret },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Conflict);
+    }
+
+    let uri = totp.provisioning_uri(&config.issuer(), &account.username, &secret);
+    let qr = match qr {
+        Some(true) => Some(qr_svg(&uri)?),
+        _ => None,
+    };
+
+    Ok(Json(EnrollmentResponse {
+        secret: totp::encode_secret(&secret),
+        uri,
+        qr,
+    }))
+}
+
+/**
+ * Confirm the pending TOTP secret with a first valid code.
+ * A new set of recovery codes is generated, replacing any previous ones,
+ * and returned in this response only.
+ *
+ * Request:
+ * ```text
+ * POST /auth/otp/enrollment/confirmation HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "otp": "<One-time Password>"
+ * }
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "recovery_codes": ["<Recovery-Code>", ...]
+ * }
+ * ```
+ **/
+#[post("/otp/enrollment/confirmation", data = "<json_request_body>")]
+pub async fn confirm(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<ConfirmationRequest>,
+) -> Result<Json<ConfirmationResponse>, Status> {
+    let confirmation_request = json_request_body.into_inner();
+    let account = &authorization.account;
+
+    let recovery_codes = super::confirm(config, database, account, &confirmation_request.otp).await?;
+
+    Ok(Json(ConfirmationResponse { recovery_codes }))
+}
+
+/**
+ * Disable TOTP of the authorized account, including any pending enrollment,
+ * and invalidate the recovery codes.
+ *
+ * Request:
+ * ```text
+ * DELETE /auth/otp HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "otp": "<One-time Password>"
+ * }
+ * ```
+ * Where [otp] can be replaced by ["code": "<Recovery-Code>"].
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Responded with 400 Bad Request if neither is specified, 401 Unauthorized if it is invalid,
+ * and 409 Conflict while multi-factor authentication is required.
+ **/
+#[delete("/otp", data = "<json_request_body>")]
+pub async fn disable(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<DisableRequest>,
+) -> Result<Status, Status> {
+    let disable_request = json_request_body.into_inner();
+    let account = &authorization.account;
+    if account.mfa_required {
+        return Err(Status::Conflict);
+    }
+    if account.onetime_password_secret.is_none() {
+        return Err(Status::NotFound);
+    }
+
+    match (disable_request.otp, disable_request.code) {
+        (Some(otp), _) => super::consume(config, database, account, &otp).await?,
+        (None, Some(code)) => recovery::consume(database, account, &code).await?,
+        (None, None) => return Err(Status::BadRequest),
+    }
+
+    let filter = doc! {
+        "_id": account.id,
+        "onetime_password_secret": { "$exists": true },
+        "mfa_required": { "$ne": true },
+    };
+    let update = doc! {
+        "$unset": { "onetime_password_secret": "", "recovery_codes": "" },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
+
+fn qr_svg(uri: &str) -> Result<String, Status> {
+    QrCode::new(uri)
+        .map(|qr_code| {
+            qr_code.render::<svg::Color>()
+                .min_dimensions(QR_DIMENSION, QR_DIMENSION)
+                .build()
+        })
+        .map_err(|_| Status::InternalServerError)
+}
@@ -0,0 +1,326 @@
+/**
+ * Time-based one-time password (TOTP) as per RFC 6238,
+ * built on top of the HMAC-based one-time password (HOTP) of RFC 4226.
+ *
+ * Secrets are shared with authenticator apps in RFC 4648 base32 encoding,
+ * see [encode_secret] and [decode_secret].
+ * Secrets stored before in base64 keep the earlier digest-based passwords, see [Totp::legacy].
+ **/
+use base32::Alphabet;
+use chrono::{DateTime, Utc};
+use openssl::{
+    error::ErrorStack,
+    base64,
+    hash::{hash, MessageDigest},
+    memcmp,
+    nid::Nid,
+    pkey::PKey,
+    rand::rand_bytes,
+    sign::Signer,
+};
+use rocket::http::RawStr;
+
+const SECRET_ALPHABET: Alphabet = Alphabet::Rfc4648 { padding: false };
+
+pub struct Totp {
+    message_digest: MessageDigest,
+    period: i64,
+    digits: u32,
+    legacy: bool,
+}
+
+/**
+ * Stored secret, told apart by its encoding.
+ **/
+pub enum Secret {
+    Base32(Vec<u8>),
+    /**
+     * Secret stored before base32, for the passwords of [Totp::legacy].
+     **/
+    Legacy(Vec<u8>),
+}
+
+impl Totp {
+
+    pub fn new(message_digest: MessageDigest, period: i64, digits: u32) -> Self {
+        Self { message_digest, period, digits, legacy: false }
+    }
+
+    /**
+     * Digest-based passwords predating RFC 6238, `Truncate(H(K || T))` of 6 digits in 30-second steps,
+     * for the secrets stored in base64 before.
+     **/
+    pub fn legacy(message_digest: MessageDigest) -> Self {
+        Self { message_digest, period: 30, digits: 6, legacy: true }
+    }
+
+    /**
+     * Time step counter `T = floor((Current Unix time - T0) / X)`, where `T0` is the Unix epoch.
+     **/
+    pub fn time_step(&self, timestamp: &DateTime<Utc>) -> u64 {
+        timestamp.timestamp()
+            .div_euclid(self.period)
+            .max(0) as u64
+    }
+
+    pub fn generate(&self, secret: &[u8], time_step: u64) -> Result<String, ErrorStack> {
+        if !self.legacy {
+            return hotp(secret, time_step, self.message_digest, self.digits);
+        }
+        let digest = hash(self.message_digest, &[secret, &time_step.to_be_bytes()].concat())?;
+        let password = dynamic_truncate(&digest) % 10_u32.pow(self.digits);
+        Ok(format!("{:0digits$}", password, digits = self.digits as usize))
+    }
+
+    /**
+     * Random secret with the same length as the HMAC output, as recommended by RFC 4226.
+     **/
+    pub fn generate_secret(&self) -> Result<Vec<u8>, ErrorStack> {
+        let mut secret = vec![0; self.message_digest.size()];
+        rand_bytes(&mut secret)?;
+        Ok(secret)
+    }
+
+    /**
+     * Key URI for provisioning authenticator apps:
+     * `otpauth://totp/<Issuer>:<Account>?secret=<Secret>&issuer=<Issuer>&algorithm=<Algorithm>&digits=<Digits>&period=<Period>`
+     **/
+    pub fn provisioning_uri(&self, issuer: &str, account_name: &str, secret: &[u8]) -> String {
+        let algorithm = match self.message_digest.type_() {
+            Nid::SHA256 => "SHA256",
+            Nid::SHA512 => "SHA512",
+            _ => "SHA1",
+        };
+        let issuer = RawStr::new(issuer).percent_encode();
+        let account_name = RawStr::new(account_name).percent_encode();
+        format!(
+            "otpauth://totp/{issuer}:{account_name}?secret={secret}&issuer={issuer}&algorithm={algorithm}&digits={digits}&period={period}",
+            secret = encode_secret(secret),
+            digits = self.digits,
+            period = self.period,
+        )
+    }
+
+    /**
+     * Find the time step within ±[window] steps around [timestamp] whose password matches [submitted].
+     * Steps closer to the current one are checked first.
+     **/
+    pub fn verify(
+        &self,
+        secret: &[u8],
+        submitted: &str,
+        timestamp: &DateTime<Utc>,
+        window: u64,
+    ) -> Result<Option<u64>, ErrorStack> {
+        let time_step = self.time_step(timestamp);
+        let candidates = (1..=window)
+            .flat_map(|distance| [time_step.checked_sub(distance), time_step.checked_add(distance)])
+            .flatten();
+        let candidates = std::iter::once(time_step).chain(candidates);
+        for candidate in candidates {
+            if matches(&self.generate(secret, candidate)?, submitted) {
+                return Ok(Some(candidate));
+            }
+        }
+        Ok(None)
+    }
+
+}
+
+/**
+ * HOTP value of [counter], `HOTP(K, C) = Truncate(HMAC(K, C))`.
+ **/
+pub fn hotp(
+    secret: &[u8],
+    counter: u64,
+    message_digest: MessageDigest,
+    digits: u32,
+) -> Result<String, ErrorStack> {
+    let hmac_key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(message_digest, &hmac_key)?;
+    signer.update(&counter.to_be_bytes())?;
+    let hmac = signer.sign_to_vec()?;
+
+    let binary_code = dynamic_truncate(&hmac);
+    let hotp = binary_code % 10_u32.pow(digits);
+    Ok(format!("{:0digits$}", hotp, digits = digits as usize))
+}
+
+/**
+ * Constant-time comparison between the generated and the submitted passwords.
+ **/
+pub fn matches(generated: &str, submitted: &str) -> bool {
+    generated.len() == submitted.len() &&
+        memcmp::eq(generated.as_bytes(), submitted.as_bytes())
+}
+
+pub fn encode_secret(secret: &[u8]) -> String {
+    base32::encode(SECRET_ALPHABET, secret)
+}
+
+/**
+ * Decode a base32 secret, tolerating lowercase letters, whitespaces and paddings
+ * as presented by most authenticator apps.
+ **/
+pub fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let normalized = secret.chars()
+        .filter(|char| !char.is_whitespace() && *char != '=')
+        .map(|char| char.to_ascii_uppercase())
+        .collect::<String>();
+    base32::decode(SECRET_ALPHABET, &normalized)
+}
+
+/**
+ * Decode a stored secret, written in uppercase base32 by [encode_secret],
+ * or else in base64 as before, whose random characters virtually never all fall in the base32 alphabet.
+ **/
+pub fn decode_stored_secret(secret: &str) -> Option<Secret> {
+    if secret.bytes().all(|byte| matches!(byte, b'A'..=b'Z' | b'2'..=b'7')) {
+        decode_secret(secret).map(Secret::Base32)
+    } else {
+        base64::decode_block(secret).ok().map(Secret::Legacy)
+    }
+}
+
+fn dynamic_truncate(hmac: &[u8]) -> u32 {
+    let offset = (hmac[hmac.len() - 1] & 0xF) as usize;
+    extract_information(offset).iter()
+        .map(|information| hmac.extract(*information))
+        .fold(0, |acc, val| acc | val)
+}
+
+fn extract_information(offset: usize) -> Vec<(usize, u32, u32)> {
+    vec![
+        (offset, 0x7F, 24),
+        (offset + 1, 0xFF, 16),
+        (offset + 2, 0xFF, 8),
+        (offset + 3, 0xFF, 0)
+    ]
+}
+
+trait Hashing {
+    fn extract(&self, information: (usize, u32, u32)) -> u32;
+}
+
+impl Hashing for [u8] {
+    fn extract(&self, information: (usize, u32, u32)) -> u32 {
+        let (index, mask, shifting) = information;
+        (self[index] as u32 & mask) << shifting
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+    use openssl::hash::MessageDigest;
+
+    use openssl::base64;
+
+    use super::{decode_secret, decode_stored_secret, encode_secret, hotp, matches, Secret, Totp};
+
+    const SEED_SHA1: &[u8] = b"12345678901234567890";
+    const SEED_SHA256: &[u8] = b"12345678901234567890123456789012";
+    const SEED_SHA512: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    // RFC 4226 Appendix D
+    #[test]
+    fn test_hotp_rfc4226() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314",
+            "254676", "287922", "162583", "399871", "520489",
+        ];
+        for (counter, expected) in expected.iter().enumerate() {
+            let hotp = hotp(SEED_SHA1, counter as u64, MessageDigest::sha1(), 6).unwrap();
+            assert_eq!(hotp, *expected, "Counter={}", counter);
+        }
+    }
+
+    // RFC 6238 Appendix B
+    #[test]
+    fn test_totp_rfc6238() {
+        let expected = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        let sha1 = Totp::new(MessageDigest::sha1(), 30, 8);
+        let sha256 = Totp::new(MessageDigest::sha256(), 30, 8);
+        let sha512 = Totp::new(MessageDigest::sha512(), 30, 8);
+        for (timestamp, expected_sha1, expected_sha256, expected_sha512) in expected {
+            let timestamp = DateTime::from_timestamp(timestamp, 0).unwrap();
+            let time_step = sha1.time_step(&timestamp);
+            assert_eq!(sha1.generate(SEED_SHA1, time_step).unwrap(), expected_sha1, "Time={}", timestamp);
+            assert_eq!(sha256.generate(SEED_SHA256, time_step).unwrap(), expected_sha256, "Time={}", timestamp);
+            assert_eq!(sha512.generate(SEED_SHA512, time_step).unwrap(), expected_sha512, "Time={}", timestamp);
+        }
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_secret_base32() {
+        let encoded = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert_eq!(encode_secret(SEED_SHA1), encoded);
+        assert_eq!(decode_secret(encoded).unwrap(), SEED_SHA1);
+        assert_eq!(decode_secret("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(), SEED_SHA1);
+        assert!(decode_secret("not-base32!").is_none());
+    }
+
+    #[test]
+    fn test_stored_secret() {
+        let encoded = encode_secret(SEED_SHA1);
+        assert!(matches!(decode_stored_secret(&encoded), Some(Secret::Base32(secret)) if secret == SEED_SHA1));
+        let encoded = base64::encode_block(SEED_SHA1);
+        assert!(matches!(decode_stored_secret(&encoded), Some(Secret::Legacy(secret)) if secret == SEED_SHA1));
+        assert!(decode_stored_secret("not-base64!").is_none());
+    }
+
+    #[test]
+    fn test_legacy() {
+        let totp = Totp::legacy(MessageDigest::sha256());
+        let timestamp = DateTime::from_timestamp(1111111109, 0).unwrap();
+        let time_step = totp.time_step(&timestamp);
+        assert_eq!(time_step, 37037036);
+        // Truncated SHA-256 of the secret followed by the big-endian time step
+        assert_eq!(totp.generate(SEED_SHA1, time_step).unwrap(), "085149");
+        assert_eq!(totp.verify(SEED_SHA1, "085149", &timestamp, 0).unwrap(), Some(time_step));
+    }
+
+    #[test]
+    fn test_verify_window() {
+        let totp = Totp::new(MessageDigest::sha1(), 30, 8);
+        let timestamp = DateTime::from_timestamp(1111111109, 0).unwrap();
+        let time_step = totp.time_step(&timestamp);
+        let previous = totp.generate(SEED_SHA1, time_step - 1).unwrap();
+        let next = totp.generate(SEED_SHA1, time_step + 1).unwrap();
+        let far = totp.generate(SEED_SHA1, time_step + 2).unwrap();
+
+        assert_eq!(totp.verify(SEED_SHA1, "07081804", &timestamp, 0).unwrap(), Some(time_step));
+        assert_eq!(totp.verify(SEED_SHA1, &previous, &timestamp, 0).unwrap(), None);
+        assert_eq!(totp.verify(SEED_SHA1, &previous, &timestamp, 1).unwrap(), Some(time_step - 1));
+        assert_eq!(totp.verify(SEED_SHA1, &next, &timestamp, 1).unwrap(), Some(time_step + 1));
+        assert_eq!(totp.verify(SEED_SHA1, &far, &timestamp, 1).unwrap(), None);
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_provisioning_uri() {
+        let totp = Totp::new(MessageDigest::sha256(), 30, 6);
+        let uri = totp.provisioning_uri("Cloudy", "john doe", SEED_SHA1);
+        assert_eq!(
+            uri,
+            "otpauth://totp/Cloudy:john%20doe?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+                &issuer=Cloudy&algorithm=SHA256&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("287082", "287082"));
+        assert!(!matches("287082", "287083"));
+        assert!(!matches("287082", "28708"));
+    }
+
+}
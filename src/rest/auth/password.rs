@@ -0,0 +1,295 @@
+#![allow(private_interfaces)]
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use mongodb::bson::doc;
+use openssl::rand::rand_bytes;
+use rocket::{http::Status, serde::json::Json};
+use serde::Deserialize;
+use tokio::task::spawn_blocking;
+
+use crate::{
+    state::{
+        database::collection::{account::OnetimePasswordSecret, token::Issuer},
+        scope::AccountWrite,
+        Client,
+        Config,
+        ConfigState,
+        DatabaseState,
+        JsonWebTokenState,
+        RequireScope,
+    },
+    str_vec,
+};
+
+use super::{
+    issuance,
+    multi_factor::{self, Login},
+    onetime_password,
+};
+
+#[derive(Deserialize)]
+struct PasswordRequest {
+    usr: String,
+    pwd: String,
+    #[serde(default)]
+    otp: Option<String>,
+    #[serde(default)]
+    scope: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct UpdateRequest {
+    #[serde(default)]
+    current: Option<String>,
+    new: String,
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /auth/password HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "usr": "<Username>",
+ *     "pwd": "<Password>",
+ *     "otp": "<One-time Password>",
+ *     "scope": ["<Scope>", ...]
+ * }
+ * ```
+ * Where the optional [otp] completes the login with the second factor at once.
+ * Otherwise a ticket is responded if the account requires multi-factor authentication,
+ * or "auth.password.require-otp" is enabled, in which case accounts without
+ * a confirmed TOTP secret cannot log in with password.
+ *
+ * Successful Response: [Login]
+ *
+ * All errors are responded with HTTP status codes only.
+ **/
+#[post("/password", data = "<json_request_body>")]
+pub(super) async fn verify(
+    config: &ConfigState,
+    database: &DatabaseState,
+    jsonwebtoken: &JsonWebTokenState,
+    client: Client,
+    json_request_body: Json<PasswordRequest>,
+) -> Result<Json<Login>, Status> {
+    let password_request = json_request_body.into_inner();
+
+    let account = database.find_account_by_username(&password_request.usr)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    // Unknown accounts and accounts without password take as long as a wrong password
+    let password_hash = account.as_ref()
+        .and_then(|account| account.password.clone())
+        .unwrap_or(DUMMY_PASSWORD_HASH.into());
+    let is_verified = verify_hash(password_request.pwd, password_hash).await?;
+    let Some(account) = account.filter(|account| is_verified && account.password.is_some()) else {
+        return Err(Status::Unauthorized);
+    };
+
+    if let Some(otp) = password_request.otp {
+        if !account.onetime_password_secret.as_ref()
+            .is_some_and(OnetimePasswordSecret::is_confirmed) {
+            return Err(Status::Forbidden);
+        }
+        onetime_password::consume(config, database, &account, &otp).await?;
+
+        let issuer = Issuer::MultiFactor {
+            first: Box::new(Issuer::Password),
+            second: Box::new(Issuer::OnetimePassword),
+        };
+        let Json(issued_token) = issuance::issue(
+            database, jsonwebtoken, &client, account.id, issuer, password_request.scope,
+        ).await?;
+        return Ok(Json(Login::Issued(issued_token)));
+    }
+
+    multi_factor::login(
+        config,
+        database,
+        jsonwebtoken,
+        &client,
+        &account,
+        Issuer::Password,
+        password_request.scope,
+    ).await
+}
+
+/**
+ * Set or change the password of the authorized account.
+ *
+ * Request:
+ * ```text
+ * PUT /auth/password HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "current": "<Current-Password>",
+ *     "new": "<New-Password>"
+ * }
+ * ```
+ * Where [current] is required only if a password has already been set.
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Only sessions logged in recently can update the password, never personal access tokens.
+ * Responded with 400 Bad Request if the new password is shorter than "auth.password.min-length",
+ * and 403 Forbidden if the login is not recent, or the current password is missing or incorrect.
+ **/
+#[put("/password", data = "<json_request_body>")]
+pub(super) async fn update(
+    config: &ConfigState,
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    json_request_body: Json<UpdateRequest>,
+) -> Result<Status, Status> {
+    let update_request = json_request_body.into_inner();
+    if !authorization.is_recent_login() {
+        return Err(Status::Forbidden);
+    }
+    let account = authorization.into_inner().account;
+
+    if update_request.new.chars().count() < config.password_min_length() {
+        return Err(Status::BadRequest);
+    }
+
+    if let Some(password_hash) = account.password.clone() {
+        let current = update_request.current
+            .ok_or(Status::Forbidden)?;
+        if !verify_hash(current, password_hash).await? {
+            return Err(Status::Forbidden);
+        }
+    }
+
+    let new_password_hash = hash(update_request.new).await?;
+
+    // Never overwrite a password changed in the meantime
+    let filter = match &account.password {
+        Some(password_hash) => doc! { "_id": account.id, "password": password_hash },
+        None => doc! { "_id": account.id, "password": { "$exists": false } },
+    };
+    let update = doc! {
+        "$set": { "password": new_password_hash },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::Conflict);
+    }
+
+    Ok(Status::NoContent)
+}
+
+// 16 random bytes of salt, as recommended for Argon2
+const SALT_BYTES: usize = 16;
+
+// Hash of a random password with the default parameters, verified against in place of a missing password
+const DUMMY_PASSWORD_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$POYDv2F8OKNDioIIEcvZsg$u95E5Rq6+a/GrjdeCnXjB7/HGyorSy3b7iqxV7tL+uU";
+
+/**
+ * Argon2id hash of [password] with a random salt, encoded as a PHC string.
+ * Hashing is CPU-bound, so it runs on the blocking thread pool.
+ **/
+async fn hash(password: String) -> Result<String, Status> {
+    let mut salt_bytes = [0; SALT_BYTES];
+    rand_bytes(&mut salt_bytes)
+        .map_err(|_| Status::InternalServerError)?;
+    let salt = SaltString::encode_b64(&salt_bytes)
+        .map_err(|_| Status::InternalServerError)?;
+
+    spawn_blocking(move || {
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|password_hash| password_hash.to_string())
+    })
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .map_err(|_| Status::InternalServerError)
+}
+
+/**
+ * Verify [password] against the PHC string [password_hash],
+ * with the parameters recorded in it.
+ **/
+async fn verify_hash(password: String, password_hash: String) -> Result<bool, Status> {
+    spawn_blocking(move || {
+        let password_hash = PasswordHash::new(&password_hash)
+            .map_err(|_| Status::InternalServerError)?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok())
+    })
+        .await
+        .map_err(|_| Status::InternalServerError)?
+}
+
+// Minimum number of characters of a password
+const MIN_LENGTH: usize = 12;
+
+/**
+ * Password config keys in [Config].
+ *
+ * Require a valid TOTP along with the password: "auth.password.require-otp",
+ * either "true" or "false", disabled if not specified.
+ *
+ * Minimum number of characters: "auth.password.min-length", set as [MIN_LENGTH] if not specified.
+ **/
+pub(super) trait Password {
+    fn password_requires_otp(&self) -> bool;
+    fn password_min_length(&self) -> usize;
+}
+
+impl Password for Config {
+    fn password_requires_otp(&self) -> bool {
+        self.get(str_vec!["auth", "password", "require-otp"])
+            .and_then(|require_otp| require_otp.parse::<bool>().ok())
+            .unwrap_or(false)
+    }
+
+    fn password_min_length(&self) -> usize {
+        self.get(str_vec!["auth", "password", "min-length"])
+            .and_then(|min_length| min_length.parse::<usize>().ok())
+            .unwrap_or(MIN_LENGTH)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let password_hash = hash("correct horse battery staple".into()).await.unwrap();
+        assert!(password_hash.starts_with("$argon2id$"));
+        assert!(verify_hash("correct horse battery staple".into(), password_hash.clone()).await.unwrap());
+        assert!(!verify_hash("incorrect horse battery staple".into(), password_hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_salted() {
+        let password_hash = hash("password".into()).await.unwrap();
+        let other_password_hash = hash("password".into()).await.unwrap();
+        assert_ne!(password_hash, other_password_hash);
+    }
+
+    #[tokio::test]
+    async fn test_dummy_hash() {
+        let password_hash = hash("password".into()).await.unwrap();
+        assert_eq!(
+            PasswordHash::new(&password_hash).unwrap().params,
+            PasswordHash::new(DUMMY_PASSWORD_HASH).unwrap().params,
+        );
+        assert!(!verify_hash("password".into(), DUMMY_PASSWORD_HASH.into()).await.unwrap());
+    }
+}
@@ -0,0 +1,199 @@
+#![allow(private_interfaces)]
+use mongodb::bson::doc;
+use openssl::{error::ErrorStack, rand::rand_bytes};
+use rocket::{http::Status, serde::json::Json};
+use serde::Deserialize;
+
+use crate::{
+    state::{
+        database::collection::{token::Issuer, Account},
+        secret,
+        Client,
+        Config,
+        Database,
+        DatabaseState,
+        JsonWebTokenState,
+    },
+    str_vec,
+};
+
+use super::issuance::{self, IssuedToken};
+
+#[derive(Deserialize)]
+struct RecoveryRequest {
+    usr: String,
+    code: String,
+    #[serde(default)]
+    scope: Option<Vec<String>>,
+}
+
+/**
+ * Request:
+ * ```text
+ * POST /auth/recovery HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "usr": "<Username>",
+ *     "code": "<Recovery-Code>",
+ *     "scope": ["<Scope>", ...]
+ * }
+ * ```
+ *
+ * Successful Response: [IssuedToken]
+ *
+ * Each recovery code is redeemable once only.
+ * Accounts requiring multi-factor authentication redeem codes in place of
+ * one-time passwords at [super::multi_factor::verify] instead, and are responded with 403 Forbidden.
+ * All errors are responded with HTTP status codes only.
+ **/
+#[post("/recovery", data = "<json_request_body>")]
+pub(super) async fn redeem(
+    database: &DatabaseState,
+    jsonwebtoken: &JsonWebTokenState,
+    client: Client,
+    json_request_body: Json<RecoveryRequest>,
+) -> Result<Json<IssuedToken>, Status> {
+    let recovery_request = json_request_body.into_inner();
+
+    let account = database.find_account_by_username(&recovery_request.usr)
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::Unauthorized)?;
+    if account.mfa_required {
+        return Err(Status::Forbidden);
+    }
+
+    consume(database, &account, &recovery_request.code).await?;
+
+    issuance::issue(
+        database, jsonwebtoken, &client, account.id, Issuer::RecoveryCode, recovery_request.scope,
+    ).await
+}
+
+/**
+ * Remove [code] from the unused recovery codes of [account],
+ * responded with 401 Unauthorized if it is not one of them.
+ **/
+pub(super) async fn consume(
+    database: &Database,
+    account: &Account,
+    code: &str,
+) -> Result<(), Status> {
+    let code_hash = hash(code);
+
+    // Remove the code atomically, so that concurrent requests cannot redeem it twice
+    let filter = doc! {
+        "_id": account.id,
+        "recovery_codes": &code_hash,
+    };
+    let update = doc! {
+        "$pull": { "recovery_codes": &code_hash },
+    };
+    let update_result = database.collections.account.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.modified_count == 0 {
+        return Err(Status::Unauthorized);
+    }
+    Ok(())
+}
+
+// Random bytes per code, encoded into 16 base32 characters
+const CODE_BYTES: usize = 10;
+const CODE_GROUP_LENGTH: usize = 4;
+const CODE_COUNT: usize = 10;
+
+/**
+ * Generate a new set of recovery codes, returned along with their hashes for storage.
+ * Codes are formatted as `XXXX-XXXX-XXXX-XXXX`.
+ **/
+pub(in crate::rest) fn generate(config: &Config) -> Result<(Vec<String>, Vec<String>), ErrorStack> {
+    let mut codes = Vec::new();
+    for _ in 0..config.recovery_code_count() {
+        let mut code_bytes = [0; CODE_BYTES];
+        rand_bytes(&mut code_bytes)?;
+        let code = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &code_bytes)
+            .as_bytes()
+            .chunks(CODE_GROUP_LENGTH)
+            .map(|group| String::from_utf8_lossy(group).into_owned())
+            .collect::<Vec<String>>()
+            .join("-");
+        codes.push(code);
+    }
+    let hashes = codes.iter()
+        .map(|code| hash(code))
+        .collect();
+    Ok((codes, hashes))
+}
+
+/**
+ * [secret::hash] of the normalized code, ignoring cases, hyphens and whitespaces.
+ **/
+fn hash(code: &str) -> String {
+    let normalized = code.chars()
+        .filter(|char| char.is_ascii_alphanumeric())
+        .map(|char| char.to_ascii_uppercase())
+        .collect::<String>();
+    secret::hash(&normalized)
+}
+
+trait Recovery {
+    fn recovery_code_count(&self) -> usize;
+}
+
+impl Recovery for Config {
+    /**
+     * Number of recovery codes generated at once: "auth.recovery.count",
+     * set as [CODE_COUNT] if not specified.
+     **/
+    fn recovery_code_count(&self) -> usize {
+        self.get(str_vec!["auth", "recovery", "count"])
+            .and_then(|count| count.parse::<usize>().ok())
+            .filter(|count| *count > 0)
+            .unwrap_or(CODE_COUNT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let (codes, hashes) = generate(&Config::of_pairs(&[])).unwrap();
+        assert_eq!(codes.len(), CODE_COUNT);
+        assert_eq!(hashes.len(), CODE_COUNT);
+        for (code, code_hash) in codes.iter().zip(&hashes) {
+            let groups = code.split('-').collect::<Vec<&str>>();
+            assert_eq!(groups.len(), 4, "Code={}", code);
+            assert!(groups.iter().all(|group| group.len() == CODE_GROUP_LENGTH), "Code={}", code);
+            assert!(code.chars().all(|char| matches!(char, 'A'..='Z' | '2'..='7' | '-')), "Code={}", code);
+            assert_eq!(&hash(code), code_hash);
+        }
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), CODE_COUNT);
+
+        let (codes, _) = generate(&Config::of_pairs(&[("auth.recovery.count", "3")])).unwrap();
+        assert_eq!(codes.len(), 3);
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_hash() {
+        let code_hash = hash("ABCD-EFGH-2345-67AB");
+        assert_eq!(hash("abcd-efgh-2345-67ab"), code_hash);
+        assert_eq!(hash("ABCDEFGH234567AB"), code_hash);
+        assert_eq!(hash(" abcd efgh 2345 67ab "), code_hash);
+        assert_ne!(hash("ABCD-EFGH-2345-67AC"), code_hash);
+    }
+
+    #[test]
+    fn test_recovery_code_count() {
+        assert_eq!(Config::of_pairs(&[]).recovery_code_count(), CODE_COUNT);
+        assert_eq!(Config::of_pairs(&[("auth.recovery.count", "0")]).recovery_code_count(), CODE_COUNT);
+        assert_eq!(Config::of_pairs(&[("auth.recovery.count", "5")]).recovery_code_count(), 5);
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(private_interfaces)]
+use chrono::Utc;
+use mongodb::{
+    bson,
+    bson::{doc, oid::ObjectId},
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::Deserialize;
+
+use crate::state::{
+    database::collection::token::State,
+    secret,
+    Client,
+    Database,
+    DatabaseState,
+    JsonWebTokenState,
+};
+
+use super::issuance::{self, IssuedToken};
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+/**
+ * Exchange a refresh token for a new access token and a new refresh token.
+ * The presented token is rotated and can never be used again.
+ *
+ * Request:
+ * ```text
+ * POST /auth/refresh HTTP/<HTTP-Version>
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "refresh_token": "<Refresh Token String>"
+ * }
+ * ```
+ *
+ * Successful Response: [IssuedToken]
+ *
+ * Presenting a rotated refresh token again revokes the whole token family,
+ * as either the client or an attacker holds a stolen copy.
+ * All errors are responded with HTTP status codes only.
+ **/
+#[post("/refresh", data = "<json_request_body>")]
+pub(super) async fn refresh(
+    database: &DatabaseState,
+    jsonwebtoken: &JsonWebTokenState,
+    client: Client,
+    json_request_body: Json<RefreshRequest>,
+) -> Result<Json<IssuedToken>, Status> {
+    let refresh_request = json_request_body.into_inner();
+    let refresh_token_hash = secret::hash(&refresh_request.refresh_token);
+    let now_timestamp = Utc::now().timestamp_millis();
+
+    let rotated = bson::to_bson(&State::Rotated(now_timestamp))
+        .map_err(|_| Status::InternalServerError)?;
+    // Rotate atomically, so that concurrent requests cannot refresh the same token twice.
+    // Normal state is not serialized
+    let filter = doc! {
+        "refresh.hash": &refresh_token_hash,
+        "refresh.expiry": { "$gt": now_timestamp },
+        "state": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "state": rotated },
+    };
+    let token = database.collections.token.find_one_and_update(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let Some(token) = token else {
+        detect_reuse(database, &refresh_token_hash, now_timestamp).await?;
+        return Err(Status::Unauthorized);
+    };
+
+    let family_id = token.family_id();
+    issuance::issue_in_family(
+        database,
+        jsonwebtoken,
+        &client,
+        ObjectId::new(),
+        token.account,
+        token.issuer,
+        token.scope,
+        family_id,
+    ).await
+}
+
+/**
+ * Revoke every token of the family if [refresh_token_hash] belongs to an already rotated token.
+ **/
+async fn detect_reuse(
+    database: &Database,
+    refresh_token_hash: &str,
+    now_timestamp: i64,
+) -> Result<(), Status> {
+    let filter = doc! { "refresh.hash": refresh_token_hash };
+    let token = database.collections.token.find_one(filter)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    let Some(token) = token.filter(|token| matches!(token.state, State::Rotated(_))) else {
+        return Ok(());
+    };
+
+    let disabled = bson::to_bson(&State::Disabled(now_timestamp))
+        .map_err(|_| Status::InternalServerError)?;
+    let family_id = token.family_id();
+    let filter = doc! {
+        "$or": [{ "_id": family_id }, { "family": family_id }],
+        "state": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "state": disabled },
+    };
+    database.collections.token.update_many(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(())
+}
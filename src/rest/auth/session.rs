@@ -0,0 +1,197 @@
+use chrono::Utc;
+use mongodb::{
+    bson,
+    bson::{doc, oid::ObjectId},
+};
+use rocket::{http::Status, serde::json::Json};
+use serde::Serialize;
+
+use crate::state::{
+    database::collection::{
+        token::{Issuer, State},
+        Token,
+    },
+    scope::{AccountRead, AccountWrite},
+    Authorization,
+    DatabaseState,
+    RequireScope,
+};
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    id: String,
+    issuer: Issuer,
+    creation: i64,
+    expiry: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_expiry: Option<i64>,
+    state: State,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_seen: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Vec<String>>,
+    current: bool,
+}
+
+impl SessionResponse {
+    fn of_token(token: Token, current_token_id: Option<ObjectId>) -> Self {
+        Self {
+            id: token.id.to_hex(),
+            issuer: token.issuer,
+            creation: token.id.timestamp().timestamp_millis(),
+            expiry: token.expiry,
+            refresh_expiry: token.refresh.as_ref().map(|refresh| refresh.expiry),
+            state: token.state,
+            user_agent: token.user_agent,
+            ip: token.ip,
+            last_seen: token.last_seen,
+            scope: token.scope,
+            current: current_token_id == Some(token.id),
+        }
+    }
+}
+
+/**
+ * List the unexpired sessions of the authorized account, including the revoked ones.
+ * A session is unexpired while either its access token or its refresh token is.
+ *
+ * Request:
+ * ```text
+ * GET /auth/sessions HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * [
+ *     {
+ *         "id": "<Token-Id-Hex>",
+ *         "issuer": <Issuer>,
+ *         "creation": <Timestamp-Milliseconds>,
+ *         "expiry": <Timestamp-Seconds>,
+ *         "refresh_expiry": <Timestamp-Milliseconds>,
+ *         "state": "Normal" | { "Disabled": <Timestamp-Milliseconds> },
+ *         "user_agent": "<User-Agent>",
+ *         "ip": "<Client-IP>",
+ *         "last_seen": <Timestamp-Milliseconds>,
+ *         "scope": ["<Scope>", ...],
+ *         "current": <Whether-Current-Session>
+ *     },
+ *     ...
+ * ]
+ * ```
+ **/
+#[get("/sessions")]
+pub async fn list(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountRead>,
+) -> Result<Json<Vec<SessionResponse>>, Status> {
+    let now = Utc::now();
+    // Sessions are alive while either token is, rotated tokens are superseded by their successors
+    let filter = doc! {
+        "account": authorization.account.id,
+        "$or": [
+            { "expiry": { "$gt": now.timestamp() } },
+            { "refresh.expiry": { "$gt": now.timestamp_millis() } },
+        ],
+        "state.Rotated": { "$exists": false },
+    };
+    let mut cursor = database.collections.token.find(filter)
+        .sort(doc! { "_id": -1 })
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let mut sessions = Vec::new();
+    while cursor.advance().await.map_err(|_| Status::InternalServerError)? {
+        let token = cursor.deserialize_current()
+            .map_err(|_| Status::InternalServerError)?;
+        sessions.push(SessionResponse::of_token(token, authorization.session().map(|token| token.id)));
+    }
+    Ok(Json(sessions))
+}
+
+/**
+ * Disable the token of the current session.
+ *
+ * Request:
+ * ```text
+ * POST /auth/logout HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ **/
+#[post("/logout")]
+pub async fn logout(
+    database: &DatabaseState,
+    authorization: Authorization,
+) -> Result<Status, Status> {
+    // Personal access tokens are revoked through their own endpoint
+    let token = authorization.session()
+        .ok_or(Status::BadRequest)?;
+    disable(database, token.id, authorization.account.id).await
+}
+
+/**
+ * Revoke any session of the authorized account.
+ *
+ * Request:
+ * ```text
+ * DELETE /auth/tokens/<Token-Id-Hex> HTTP/<HTTP-Version>
+ * Authorization: <JWT Token String>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 204 No Content
+ * ```
+ *
+ * Responded with 404 Not Found if the token is not an active token of the account.
+ **/
+#[delete("/tokens/<id>")]
+pub async fn revoke(
+    database: &DatabaseState,
+    authorization: RequireScope<AccountWrite>,
+    id: &str,
+) -> Result<Status, Status> {
+    let token_id = ObjectId::parse_str(id)
+        .map_err(|_| Status::BadRequest)?;
+    disable(database, token_id, authorization.account.id).await
+}
+
+async fn disable(
+    database: &DatabaseState,
+    token_id: ObjectId,
+    account_id: ObjectId,
+) -> Result<Status, Status> {
+    let state = bson::to_bson(&State::Disabled(Utc::now().timestamp_millis()))
+        .map_err(|_| Status::InternalServerError)?;
+    // Normal state is not serialized
+    let filter = doc! {
+        "_id": token_id,
+        "account": account_id,
+        "state": { "$exists": false },
+    };
+    let update = doc! {
+        "$set": { "state": state },
+    };
+    let update_result = database.collections.token.update_one(filter, update)
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if update_result.matched_count == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,230 @@
+/**
+ * OpenSSH public keys in `authorized_keys` format, and SSH signatures produced by
+ * `ssh-keygen -Y sign -n cloudy`, as specified in OpenSSH PROTOCOL.sshsig.
+ *
+ * Binary data are in the SSH wire encoding of RFC 4251.
+ **/
+use openssl::{
+    base64,
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey, EcPoint},
+    ecdsa::EcdsaSig,
+    error::ErrorStack,
+    hash::{hash, MessageDigest},
+    nid::Nid,
+    pkey::{Id, PKey, Public},
+    rsa::Rsa,
+    sign::Verifier,
+};
+
+/**
+ * Namespace the signatures must be created for, i.e. `-n cloudy`.
+ **/
+pub const NAMESPACE: &str = "cloudy";
+
+const MAGIC_PREAMBLE: &[u8] = b"SSHSIG";
+const SIGNATURE_VERSION: u32 = 1;
+const ARMOR_BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+const ARMOR_END: &str = "-----END SSH SIGNATURE-----";
+
+const KEY_TYPE_ED25519: &str = "ssh-ed25519";
+const KEY_TYPE_RSA: &str = "ssh-rsa";
+const KEY_TYPE_ECDSA_P256: &str = "ecdsa-sha2-nistp256";
+const KEY_TYPE_ECDSA_P384: &str = "ecdsa-sha2-nistp384";
+
+pub struct SshSignature {
+    public_key: PKey<Public>,
+    namespace: String,
+    reserved: Vec<u8>,
+    hash_algorithm: String,
+    signature_type: String,
+    signature: Vec<u8>,
+}
+
+pub fn is_authorized_key(key: &str) -> bool {
+    [KEY_TYPE_ED25519, KEY_TYPE_RSA, KEY_TYPE_ECDSA_P256, KEY_TYPE_ECDSA_P384].iter()
+        .any(|key_type| key.trim_start().starts_with(key_type))
+}
+
+pub fn is_armored_signature(signature: &str) -> bool {
+    signature.trim_start().starts_with(ARMOR_BEGIN)
+}
+
+/**
+ * Parse an `authorized_keys` line `<Key-Type> <Base64-Key> [Comment]`, options are not supported.
+ **/
+pub fn parse_authorized_key(line: &str) -> Option<PKey<Public>> {
+    let mut fields = line.split_whitespace();
+    let key_type = fields.next()?;
+    let blob = base64::decode_block(fields.next()?).ok()?;
+    if Reader::new(&blob).read_str()? != key_type {
+        return None;
+    }
+    parse_public_key(&blob)
+}
+
+/**
+ * Parse an armored SSH signature.
+ **/
+pub fn parse_signature(armored: &str) -> Option<SshSignature> {
+    let armored = armored.trim();
+    let body = armored.strip_prefix(ARMOR_BEGIN)?
+        .strip_suffix(ARMOR_END)?
+        .split_whitespace()
+        .collect::<String>();
+    let blob = base64::decode_block(&body).ok()?;
+
+    let mut reader = Reader::new(&blob);
+    if reader.read_bytes(MAGIC_PREAMBLE.len())? != MAGIC_PREAMBLE ||
+        reader.read_u32()? != SIGNATURE_VERSION {
+        return None;
+    }
+    let public_key = parse_public_key(reader.read_string()?)?;
+    let namespace = reader.read_str()?.to_string();
+    let reserved = reader.read_string()?.to_vec();
+    let hash_algorithm = reader.read_str()?.to_string();
+
+    let mut signature_reader = Reader::new(reader.read_string()?);
+    let signature_type = signature_reader.read_str()?.to_string();
+    let signature = signature_reader.read_string()?.to_vec();
+    if !reader.is_empty() || !signature_reader.is_empty() {
+        return None;
+    }
+
+    Some(SshSignature { public_key, namespace, reserved, hash_algorithm, signature_type, signature })
+}
+
+impl SshSignature {
+
+    /**
+     * Verify the signature of [message] is made in [NAMESPACE] by [public_key].
+     **/
+    pub fn verify(&self, public_key: &PKey<Public>, message: &[u8]) -> Result<bool, ErrorStack> {
+        if self.namespace != NAMESPACE || !self.public_key.public_eq(public_key) {
+            return Ok(false);
+        }
+        let message_digest = match self.hash_algorithm.as_str() {
+            "sha256" => MessageDigest::sha256(),
+            "sha512" => MessageDigest::sha512(),
+            _ => return Ok(false),
+        };
+
+        let mut signed_data = MAGIC_PREAMBLE.to_vec();
+        write_string(&mut signed_data, self.namespace.as_bytes());
+        write_string(&mut signed_data, &self.reserved);
+        write_string(&mut signed_data, self.hash_algorithm.as_bytes());
+        write_string(&mut signed_data, &hash(message_digest, message)?);
+
+        match self.signature_type.as_str() {
+            KEY_TYPE_ED25519 => {
+                let mut verifier = Verifier::new_without_digest(public_key)?;
+                Ok(verifier.verify_oneshot(&self.signature, &signed_data).unwrap_or(false))
+            }
+            "rsa-sha2-256" => verify_digest(public_key, MessageDigest::sha256(), &self.signature, &signed_data),
+            "rsa-sha2-512" => verify_digest(public_key, MessageDigest::sha512(), &self.signature, &signed_data),
+            KEY_TYPE_ECDSA_P256 | KEY_TYPE_ECDSA_P384 => {
+                let message_digest = match self.signature_type.as_str() {
+                    KEY_TYPE_ECDSA_P256 => MessageDigest::sha256(),
+                    _ => MessageDigest::sha384(),
+                };
+                let mut reader = Reader::new(&self.signature);
+                let (Some(r), Some(s)) = (reader.read_string(), reader.read_string()) else {
+                    return Ok(false);
+                };
+                let ecdsa_signature = EcdsaSig::from_private_components(
+                    BigNum::from_slice(r)?, BigNum::from_slice(s)?,
+                )?;
+                verify_digest(public_key, message_digest, &ecdsa_signature.to_der()?, &signed_data)
+            }
+            // Including "ssh-rsa" of SHA-1, which is not accepted for signatures
+            _ => Ok(false),
+        }
+    }
+
+}
+
+fn verify_digest(
+    public_key: &PKey<Public>,
+    message_digest: MessageDigest,
+    signature: &[u8],
+    signed_data: &[u8],
+) -> Result<bool, ErrorStack> {
+    let mut verifier = Verifier::new(message_digest, public_key)?;
+    verifier.update(signed_data)?;
+    Ok(verifier.verify(signature).unwrap_or(false))
+}
+
+fn parse_public_key(blob: &[u8]) -> Option<PKey<Public>> {
+    let mut reader = Reader::new(blob);
+    let public_key = match reader.read_str()? {
+        KEY_TYPE_ED25519 => {
+            PKey::public_key_from_raw_bytes(reader.read_string()?, Id::ED25519).ok()?
+        }
+        KEY_TYPE_RSA => {
+            let exponent = BigNum::from_slice(reader.read_string()?).ok()?;
+            let modulus = BigNum::from_slice(reader.read_string()?).ok()?;
+            let rsa = Rsa::from_public_components(modulus, exponent).ok()?;
+            PKey::from_rsa(rsa).ok()?
+        }
+        key_type @ (KEY_TYPE_ECDSA_P256 | KEY_TYPE_ECDSA_P384) => {
+            let (nid, curve_name) = match key_type {
+                KEY_TYPE_ECDSA_P256 => (Nid::X9_62_PRIME256V1, "nistp256"),
+                _ => (Nid::SECP384R1, "nistp384"),
+            };
+            if reader.read_str()? != curve_name {
+                return None;
+            }
+            let group = EcGroup::from_curve_name(nid).ok()?;
+            let mut big_num_context = BigNumContext::new().ok()?;
+            let point = EcPoint::from_bytes(&group, reader.read_string()?, &mut big_num_context).ok()?;
+            let ec_key = EcKey::from_public_key(&group, &point).ok()?;
+            PKey::from_ec_key(ec_key).ok()?
+        }
+        _ => return None,
+    };
+    reader.is_empty().then_some(public_key)
+}
+
+fn write_string(buffer: &mut Vec<u8>, string: &[u8]) {
+    buffer.extend((string.len() as u32).to_be_bytes());
+    buffer.extend(string);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < length {
+            return None;
+        }
+        let (bytes, remaining) = self.bytes.split_at(length);
+        self.bytes = remaining;
+        Some(bytes)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        let bytes = self.read_bytes(4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    fn read_string(&mut self) -> Option<&'a [u8]> {
+        let length = self.read_u32()? as usize;
+        self.read_bytes(length)
+    }
+
+    fn read_str(&mut self) -> Option<&'a str> {
+        std::str::from_utf8(self.read_string()?).ok()
+    }
+
+}
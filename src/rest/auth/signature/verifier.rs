@@ -0,0 +1,488 @@
+use openssl::{
+    bn::BigNum,
+    ecdsa::EcdsaSig,
+    error::ErrorStack,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{Id, PKey, Public},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Verifier},
+};
+
+use crate::state::database::collection::account::{
+    public_key::{KeyType, Scheme},
+    PublicKey,
+};
+
+use super::ssh;
+
+// RSA_PSS_SALTLEN_AUTO, salt length is recovered from the signature
+const PSS_SALT_LENGTH_AUTO: i32 = -2;
+
+/**
+ * Verify [signature] of [message] with [public_key] under its registered [KeyType] and [Scheme].
+ * Malformed signatures and keys, or keys not of the registered [KeyType], are treated as invalid.
+ **/
+pub fn verify(
+    public_key: &PublicKey,
+    signature: &[u8],
+    message: &str,
+) -> Result<bool, ErrorStack> {
+    let Some(key) = load_public_key(public_key) else {
+        return Ok(false);
+    };
+    match (public_key.key_type, public_key.scheme) {
+        (KeyType::Rsa, Scheme::RsaRaw) => verify_rsa_raw(&key, signature, message),
+        (KeyType::Rsa, Scheme::RsaPkcs1Sha256) => verify_rsa_digest(&key, signature, message, Padding::PKCS1),
+        (KeyType::Rsa, Scheme::RsaPssSha256) => verify_rsa_digest(&key, signature, message, Padding::PKCS1_PSS),
+        (KeyType::Ed25519, _) => verify_ed25519(&key, signature, message),
+        (KeyType::EcdsaP256, _) => verify_ecdsa(&key, signature, message, &ECDSA_P256),
+        (KeyType::EcdsaP384, _) => verify_ecdsa(&key, signature, message, &ECDSA_P384),
+    }
+}
+
+/**
+ * Verify an armored SSH signature of [message], created by `ssh-keygen -Y sign -n cloudy`,
+ * with [public_key] of any [KeyType], regardless of its [Scheme].
+ **/
+pub fn verify_ssh(
+    public_key: &PublicKey,
+    armored_signature: &str,
+    message: &str,
+) -> Result<bool, ErrorStack> {
+    let Some(key) = load_public_key(public_key) else {
+        return Ok(false);
+    };
+    let Some(ssh_signature) = ssh::parse_signature(armored_signature) else {
+        return Ok(false);
+    };
+    ssh_signature.verify(&key, message.as_bytes())
+}
+
+/**
+ * Load [key] in either PEM or OpenSSH `authorized_keys` format.
+ **/
+pub fn load(key: &str) -> Option<PKey<Public>> {
+    if ssh::is_authorized_key(key) {
+        ssh::parse_authorized_key(key)
+    } else {
+        PKey::public_key_from_pem(key.as_bytes()).ok()
+    }
+}
+
+/**
+ * [KeyType] of [key], if it is loadable and of a supported type.
+ **/
+pub fn key_type_of(key: &PKey<Public>) -> Option<KeyType> {
+    [KeyType::Rsa, KeyType::Ed25519, KeyType::EcdsaP256, KeyType::EcdsaP384].into_iter()
+        .find(|key_type| is_key_type(key, *key_type))
+}
+
+/**
+ * Load [PublicKey::key], returned only if it is of the registered [KeyType].
+ **/
+fn load_public_key(public_key: &PublicKey) -> Option<PKey<Public>> {
+    load(&public_key.key)
+        .filter(|key| is_key_type(key, public_key.key_type))
+}
+
+fn is_key_type(key: &PKey<Public>, key_type: KeyType) -> bool {
+    let curve_name = || key.ec_key().ok()
+        .and_then(|ec_key| ec_key.group().curve_name());
+    match key_type {
+        KeyType::Rsa => key.id() == Id::RSA,
+        KeyType::Ed25519 => key.id() == Id::ED25519,
+        KeyType::EcdsaP256 => key.id() == Id::EC && curve_name() == Some(ECDSA_P256.nid),
+        KeyType::EcdsaP384 => key.id() == Id::EC && curve_name() == Some(ECDSA_P384.nid),
+    }
+}
+
+struct Curve {
+    nid: Nid,
+    digest: fn() -> MessageDigest,
+    // Length of each of r and s in bytes
+    field_size: usize,
+}
+
+const ECDSA_P256: Curve = Curve {
+    nid: Nid::X9_62_PRIME256V1,
+    digest: MessageDigest::sha256,
+    field_size: 32,
+};
+
+const ECDSA_P384: Curve = Curve {
+    nid: Nid::SECP384R1,
+    digest: MessageDigest::sha384,
+    field_size: 48,
+};
+
+fn verify_rsa_raw(
+    public_key: &PKey<Public>,
+    signature: &[u8],
+    message: &str,
+) -> Result<bool, ErrorStack> {
+    let rsa_public = public_key.rsa()?;
+    let mut decryption_buffer = vec![0; rsa_public.size() as usize];
+    let Ok(decrypted_len) = rsa_public.public_decrypt(signature, &mut decryption_buffer, Padding::PKCS1) else {
+        return Ok(false);
+    };
+    decryption_buffer.truncate(decrypted_len);
+    Ok(decryption_buffer == message.as_bytes())
+}
+
+fn verify_rsa_digest(
+    public_key: &PKey<Public>,
+    signature: &[u8],
+    message: &str,
+    padding: Padding,
+) -> Result<bool, ErrorStack> {
+    let mut verifier = Verifier::new(MessageDigest::sha256(), public_key)?;
+    verifier.set_rsa_padding(padding)?;
+    if padding == Padding::PKCS1_PSS {
+        verifier.set_rsa_mgf1_md(MessageDigest::sha256())?;
+        verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(PSS_SALT_LENGTH_AUTO))?;
+    }
+    verifier.update(message.as_bytes())?;
+    // Signatures of a mismatching length or padding are reported as errors by OpenSSL
+    Ok(verifier.verify(signature).unwrap_or(false))
+}
+
+fn verify_ed25519(
+    public_key: &PKey<Public>,
+    signature: &[u8],
+    message: &str,
+) -> Result<bool, ErrorStack> {
+    let mut verifier = Verifier::new_without_digest(public_key)?;
+    Ok(verifier.verify_oneshot(signature, message.as_bytes()).unwrap_or(false))
+}
+
+/**
+ * Both ASN.1 DER encoded signatures, as produced by OpenSSL,
+ * and raw `r || s` signatures, as produced by WebCrypto, are accepted.
+ **/
+fn verify_ecdsa(
+    public_key: &PKey<Public>,
+    signature: &[u8],
+    message: &str,
+    curve: &Curve,
+) -> Result<bool, ErrorStack> {
+    let der_signature = if signature.len() == curve.field_size * 2 {
+        let (r, s) = signature.split_at(curve.field_size);
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?
+            .to_der()?
+    } else {
+        signature.to_vec()
+    };
+
+    let mut verifier = Verifier::new((curve.digest)(), public_key)?;
+    verifier.update(message.as_bytes())?;
+    Ok(verifier.verify(&der_signature).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod test {
+    use mongodb::bson::oid::ObjectId;
+    use openssl::{base64, ecdsa::EcdsaSig};
+
+    use crate::state::database::collection::account::{
+        public_key::{KeyType, Scheme, Validity},
+        PublicKey,
+    };
+
+    use super::{verify, verify_ssh};
+
+    fn registered_key(key: &str, scheme: Scheme) -> PublicKey {
+        typed_key(key, KeyType::Rsa, scheme)
+    }
+
+    fn typed_key(key: &str, key_type: KeyType, scheme: Scheme) -> PublicKey {
+        PublicKey {
+            id: ObjectId::new(),
+            key: key.to_string(),
+            validity: Validity::Permanent,
+            key_type,
+            scheme,
+        }
+    }
+
+    fn raw_ecdsa_signature(der_signature: &[u8], field_size: i32) -> Vec<u8> {
+        let ecdsa_signature = EcdsaSig::from_der(der_signature).unwrap();
+        let mut raw_signature = ecdsa_signature.r().to_vec_padded(field_size).unwrap();
+        raw_signature.extend(ecdsa_signature.s().to_vec_padded(field_size).unwrap());
+        raw_signature
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_rsa_raw() {
+        let object_id_hex = "675f21efdbd4c628b5e9496a".to_string();
+
+        let public_key = "\
+                -----BEGIN PUBLIC KEY-----\n\
+                MIICIjANBgkqhkiG9w0BAQEFAAOCAg8AMIICCgKCAgEA1LzworQVsh6WEQ8pFIpH\n\
+                q3llXMd9frJcJwKcJFwIBlC1rlFnoDSAW4CK0F22BcPcp0AkAwFvD2esdxmA1lHZ\n\
+                zOG9qhefwzHhFPLhlFP62FQL88qHGPh1jqrsQuAdQA0Xmj2uX4ZWptashPx7cpyK\n\
+                o395hrr7joe4Bxs3EMDJDoWlDWmRZxN9uHnxpKD5lqJRDfAr11yGwW1RBRosQUbz\n\
+                n190FzlLlJLGm99cCiIRMYZ8Pja2Jq9x68se7N1nQPb3vdotNR7KSzlPdcVrB83c\n\
+                ozo4UzJfIdXKiscgrgSPUtu/eL4QQMQe8K/b1ZniEhMCs8bSBrBSH9veUJqeV0/i\n\
+                DifQxR0qCFbHattFvJwDWvpPlJweq65TzKnwYtUoOig9JC/RuUQFpJJcjqsMMZZy\n\
+                nYBTZJVVgQprnhN7dkvT9bHzCZnVeZkVFgU/6+1/sXwMBSQJA/dytUzuU3fC+W3U\n\
+                UdrdyGtgwmSUGHfT1gbFGlXRBqb/rthqRuLyW5j1uQOr66gWjCGMBsru1mJM5Qa6\n\
+                HwxdgLv/zhOJuYby6t+3AVmi1cQVLV3KgNn/+CCYSUllWC0gOIoYH4qWletiHUJL\n\
+                sl0hBYDBOnDPQv9VBbhuLpAOd+gLDrgDHvR65RDg6j/lke8uh5O/tfQygIMVYO8B\n\
+                AblnepcttBeKs9urwWxBkX0CAwEAAQ==\n\
+                -----END PUBLIC KEY-----\n\
+                "
+        .to_string();
+        // echo -n "675f21efdbd4c628b5e9496a" | openssl pkeyutl -sign -inkey <PrivateKeyPath> | openssl base64
+        let signature = "\
+                piZXX6AsES6AQDdV12yK0d1SYvQeO/grrxNdSsIaev63ITvToCd9dZgFH/7TkuDt\
+                DSHXGd7hfkKX4CMJBX0gEabFQh2yk878IX/FFEjZFdOxaRA78MZUULHHzt3+c9VK\
+                Zisx2h8OJDIkA/JrktazK5HDlMVSRb4HhZF0AzxAVLN0k1e9GhZyVYFwxYf9HgeT\
+                maDUm/s6jp/QcRzdBY/hE/1VW6IAJ1xTJ9rZ13/Q/tqRsUvv8p7wUfHmrbgX8kFP\
+                xjHwOKl7d/zpZhowCuDY4DsQI4bYJJ0mgyVfI5v3EPp18gsOY9lREb8UOMpL8hyx\
+                9oQTa119YthX8TbZRfRLkFfpNsTGpTRXQ3b5DrsnHBQvFcUdEtfwgb8Jc55l4M/f\
+                F8jegneo4K34uVENr5B7qgTf77zO0QP/8M/kKToKf9p9Fv4bwGdckBdpa/H+Ak5G\
+                oOTjnpUZaY0TAA+7o4puMHzWb8bdOKk+tu8KgGBkLvKmZCFXdeQpDi8PPtRUnimE\
+                oQ/FSp+2n2xksX+EnlRxzNAOnYcr9y7pAaFt2l8alLlTeHD7FS3iqWaERIyh0NFP\
+                GJP7JPqyC8VRdZUdx8ouXXrcRxsV2WBjD/7PLb1aVh19KSoMj72YGQkPcZuPIqfu\
+                m7Y6KTYPxRvNvmSEQ+ANStAPqm3bjjy8GGPu4UNES00=\
+                ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let public_key = registered_key(&public_key, Scheme::RsaRaw);
+        verify(&public_key, &signature_bytes, &object_id_hex)
+            .map(|is_valid| assert!(is_valid))
+            .unwrap_or_else(|_| panic!("Invalid RSA public key verification"));
+    }
+
+    // noinspection SpellCheckingInspection
+    const RSA_PUBLIC_KEY: &str = "\
+        -----BEGIN PUBLIC KEY-----\n\
+        MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA0VoXfJilX0sq9YdY/nt8\n\
+        99oYra9Wj9OwDi4ETkPhBTVvtHPV4BZZB0bErW1mTbQm0mK0g/Dx0wef9asWBS4y\n\
+        dVx1L6F1RLtXLKzZZ8T976Ia1HkRcnsHyXKJTTKWlM2nNocucjhtMRmkcR040/8Y\n\
+        gu9BaW/OXJRpilcGedRn8FtPpyUoONeUTea1J9es8eSwJNSq4hGSNzpD55Ezv2UH\n\
+        bSVKyyYB/eZ3j32zJQCXjKz/ZNmjJhJ40mMMF/v7N+O6F7Ysd29OTcLm6qQ5kxKQ\n\
+        ITqIaI3uaLrzk3MJ//LTYmWe0MwuS2gIaQdPPnp9CXqb0VS8IjNIlg1D0ZrsRmJ6\n\
+        WwIDAQAB\n\
+        -----END PUBLIC KEY-----\n\
+        ";
+
+    const CHALLENGE: &str = "3f6c1d0b8a9e4f2c7d5b1a0e9c8f7a6b5d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a";
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_rsa_pkcs1_sha256() {
+        // echo -n "<Challenge>" | openssl dgst -sha256 -sign <PrivateKeyPath> | openssl base64
+        let signature = "\
+            zxQimKKWyNxDsdYHtdyntz8D63GjvoavYBKsukkUo+s+7c/Lmd5PsJ9z3RLjUCfNQzxlo1LvAzRX+Jw0qUgqJHwkMdHT\
+            kQ867IXTUcYB7Zbl0M2xqbSF/mi2CANT2/AyY7cJ/sJAY7hsG+SccDHNEtYhE/kCbMZoxxm2vUHyOQoQWzztVuoFv121\
+            wWQ1mQ3fausfhQ7rk5Jcz+cmEE2wOjm/ry7NwCl/ap+l5eJ4je8q0i7DFOeXsvsbzm7bhvKDfUWwGndWFcFWeXZszTT/\
+            Re4TtQXEB0/qUJ7LkV5RXs+4ConOBxZW53g7vUtmfTpyuuuc81XwMvi1w83H7ZcdKA==\
+            ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let public_key = registered_key(RSA_PUBLIC_KEY, Scheme::RsaPkcs1Sha256);
+        assert!(verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+        assert!(!verify(&public_key, &signature_bytes, &CHALLENGE[1..]).unwrap());
+
+        // Signature of a scheme other than the registered one is rejected
+        let public_key = registered_key(RSA_PUBLIC_KEY, Scheme::RsaPssSha256);
+        assert!(!verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_rsa_pss_sha256() {
+        // echo -n "<Challenge>" | openssl dgst -sha256 -sigopt rsa_padding_mode:pss -sigopt rsa_pss_saltlen:32 \
+        //     -sign <PrivateKeyPath> | openssl base64
+        let signature = "\
+            eLkFm34/EfyCRdunoPgkYQbph8FlwYXK9kKTMh6NEuiIWJEAyy3UGt/VUS5eCQK0eJu1AvBYy7W81fcgEggLwTJ7xkNy\
+            wRzvYJ2sjkp09kE3t0TbQrdZ2HAxwkXnnF3g9+QaUT+C9bPsIfF4rCBzQlzY7WtqpvE9yxb1h+zYhKjTGAx9jjcpq03V\
+            vyTBKHpuVAG7nuk/uUr0FQXC8TJU11YJSYvSnLsRMiFux+H0XmPsoczP/nGAd+oh2n3Nq4sHpS7Bxb3/wewa0voubRPg\
+            oSV3lrY/vfrqCzcn7uIEvO+F7OsI8n0JRchL+WMT65uwDfXfjgg7biJJDokbUXzqbA==\
+            ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let public_key = registered_key(RSA_PUBLIC_KEY, Scheme::RsaPssSha256);
+        assert!(verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+        assert!(!verify(&public_key, &signature_bytes, &CHALLENGE[1..]).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ed25519() {
+        let public_key = "\
+            -----BEGIN PUBLIC KEY-----\n\
+            MCowBQYDK2VwAyEAgBWUQdFnau+DwXtn2UOrRKQN1ORjiE5yGl++cmBlEWg=\n\
+            -----END PUBLIC KEY-----\n\
+            ";
+        // echo -n "<Challenge>" > <MessagePath>
+        // openssl pkeyutl -sign -inkey <PrivateKeyPath> -rawin -in <MessagePath> | openssl base64
+        let signature = "\
+            uMP35qkrBgEiFYMmjIAyPK/tAyaH1AY2E619N5SfpRljCD4F5G3gxDsmojPHHtRWJ/8YdbaeJ6qT7RkbSFUcDA==\
+            ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let public_key = typed_key(public_key, KeyType::Ed25519, Scheme::default());
+        assert!(verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+        assert!(!verify(&public_key, &signature_bytes, &CHALLENGE[1..]).unwrap());
+
+        // Key of another type than registered is rejected
+        let public_key = typed_key(RSA_PUBLIC_KEY, KeyType::Ed25519, Scheme::default());
+        assert!(!verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ecdsa_p256() {
+        let public_key = "\
+            -----BEGIN PUBLIC KEY-----\n\
+            MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEx3L5h/YSZC9fHgQI7+Otj3Iws9fE\n\
+            h3eMVDLgun8W13KSXnDkJ/NPVkxFRUbVVl0sxI62F6fiuLeRRJH3+WnTXg==\n\
+            -----END PUBLIC KEY-----\n\
+            ";
+        // echo -n "<Challenge>" | openssl dgst -sha256 -sign <PrivateKeyPath> | openssl base64
+        let signature = "\
+            MEQCIE4aDAn1sel7OI3y3oOBGlO2v3Hv0RYMo6+qRgMNhcEfAiAILRjWBPZWkyxB61B3TKLBIITQyK/aswAcitpXBt8Yvg==\
+            ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let p256_key = typed_key(public_key, KeyType::EcdsaP256, Scheme::default());
+        assert!(verify(&p256_key, &signature_bytes, CHALLENGE).unwrap());
+        assert!(verify(&p256_key, &raw_ecdsa_signature(&signature_bytes, 32), CHALLENGE).unwrap());
+        assert!(!verify(&p256_key, &signature_bytes, &CHALLENGE[1..]).unwrap());
+
+        // Key over another curve than registered is rejected
+        let p384_key = typed_key(public_key, KeyType::EcdsaP384, Scheme::default());
+        assert!(!verify(&p384_key, &signature_bytes, CHALLENGE).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ecdsa_p384() {
+        let public_key = "\
+            -----BEGIN PUBLIC KEY-----\n\
+            MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEUGUNL4C+W7sbxE1fh3zzcufZQQtAy8hU\n\
+            ba/GawEE/f0XVqIYfbdbQUSUlDSAj4AzuqtrqsxVudEAB80udMSm+OFv/cYs8KjI\n\
+            wOuQw8UvrCIKgV/0Um81WhoIK/ZDNjLu\n\
+            -----END PUBLIC KEY-----\n\
+            ";
+        // echo -n "<Challenge>" | openssl dgst -sha384 -sign <PrivateKeyPath> | openssl base64
+        let signature = "\
+            MGYCMQDVhJFTuunrTSdR6V9lvowCADrVKzxQXeF4oCBtIffYryIVxVF9tK6Pgsafb6KAueQCMQCETGLttijOjUQo7f+z\
+            QfADbw0RVuhjsrDD8pvoPlqzOnhED3WTtdTcfFXf4L6TGUY=\
+            ";
+        let signature_bytes = base64::decode_block(signature).unwrap();
+
+        let public_key = typed_key(public_key, KeyType::EcdsaP384, Scheme::default());
+        assert!(verify(&public_key, &signature_bytes, CHALLENGE).unwrap());
+        assert!(verify(&public_key, &raw_ecdsa_signature(&signature_bytes, 48), CHALLENGE).unwrap());
+        assert!(!verify(&public_key, &signature_bytes, &CHALLENGE[1..]).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    const SSH_ED25519_PUBLIC_KEY: &str = "\
+        ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHz5jPgwfeoRGHtst6J+qW1iLawyfTDP2OFDORPq/xyu test\
+        ";
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ssh_ed25519() {
+        // printf "<Challenge>" > <MessagePath>
+        // ssh-keygen -Y sign -f <PrivateKeyPath> -n cloudy <MessagePath>
+        let signature = "\
+            -----BEGIN SSH SIGNATURE-----\n\
+            U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgfPmM+DB96hEYe2y3on6pbWItrD\n\
+            J9MM/Y4UM5E+r/HK4AAAAGY2xvdWR5AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1\n\
+            NTE5AAAAQJnZFSyTdTF17A5SlKziw9zZEvc/mpUuLzq3x9HatDTaWJKtqHZbs1mIzqAskl\n\
+            qktdWWCw/jRO7mU6nDBc75Cw4=\n\
+            -----END SSH SIGNATURE-----\n\
+            ";
+
+        let public_key = typed_key(SSH_ED25519_PUBLIC_KEY, KeyType::Ed25519, Scheme::default());
+        assert!(verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+        assert!(!verify_ssh(&public_key, signature, &CHALLENGE[1..]).unwrap());
+
+        // Key registered with another type than the OpenSSH key is rejected
+        let public_key = typed_key(SSH_ED25519_PUBLIC_KEY, KeyType::Rsa, Scheme::default());
+        assert!(!verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+
+        // Signature by another key is rejected
+        let public_key = typed_key(RSA_PUBLIC_KEY, KeyType::Rsa, Scheme::RsaPkcs1Sha256);
+        assert!(!verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ssh_namespace() {
+        // ssh-keygen -Y sign -f <PrivateKeyPath> -n other <MessagePath>
+        let signature = "\
+            -----BEGIN SSH SIGNATURE-----\n\
+            U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgfPmM+DB96hEYe2y3on6pbWItrD\n\
+            J9MM/Y4UM5E+r/HK4AAAAFb3RoZXIAAAAAAAAABnNoYTUxMgAAAFMAAAALc3NoLWVkMjU1\n\
+            MTkAAABA9PE5OKA7eA4S5Dj9w1LK5cfLXcgO6o+cRZ3pMSi7rEHrYg84uFXW5XKLBYa3tZ\n\
+            Axj+kWnvtFM3AtLN/c7xqiDw==\n\
+            -----END SSH SIGNATURE-----\n\
+            ";
+
+        let public_key = typed_key(SSH_ED25519_PUBLIC_KEY, KeyType::Ed25519, Scheme::default());
+        assert!(!verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ssh_rsa() {
+        let public_key = "\
+            ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCk9fho2FiWeC83yEBfbnecmELfnZwjlvT3rdEe1iKY8niD8lEC6gV/qTYz\
+            GAOoqJWhVy+EVwjJK+Q89rG5xvX+apZLb+Ab62X+5I05RKSv66qUewa25734sk52w45ITLfBOgEalHRiH0UW+Y4t0uvWIg1\
+            4J1n32u/GcW6fbEM696F2KhdNu6q5iw3u9Mb553JZcRf2WTl3s91yiWi1MlzjbJ0W2GF15NgSqZcCAtocTKQxCZ9j4X6QaZ\
+            Ji7E0ba5x+ZvzFvI+bOfDQre0svc/SdlPPy1H+bHygEj6g78VVh69ITg37mZZZsd/+dIO3HyppcMXMC1VjAQgVAVK1JuTd \
+            test\
+            ";
+        let signature = "\
+            -----BEGIN SSH SIGNATURE-----\n\
+            U1NIU0lHAAAAAQAAARcAAAAHc3NoLXJzYQAAAAMBAAEAAAEBAKT1+GjYWJZ4LzfIQF9ud5\n\
+            yYQt+dnCOW9Pet0R7WIpjyeIPyUQLqBX+pNjMYA6iolaFXL4RXCMkr5Dz2sbnG9f5qlktv\n\
+            4BvrZf7kjTlEpK/rqpR7BrbnvfiyTnbDjkhMt8E6ARqUdGIfRRb5ji3S69YiDXgnWffa78\n\
+            Zxbp9sQzr3oXYqF027qrmLDe70xvnncllxF/ZZOXez3XKJaLUyXONsnRbYYXXk2BKplwIC\n\
+            2hxMpDEJn2PhfpBpkmLsTRtrnH5m/MW8j5s58NCt7Sy9z9J2U8/LUf5sfKASPqDvxVWHr0\n\
+            hODfuZllmx3/50g7cfKmlwxcwLVWMBCBUBUrUm5N0AAAAGY2xvdWR5AAAAAAAAAAZzaGE1\n\
+            MTIAAAEUAAAADHJzYS1zaGEyLTUxMgAAAQAKLbd0gXk0fKXhQ5OBCzv8r2w0ckQXbQbi5d\n\
+            ybDEa9ZZRbxpZIeQ4bmm7jHthPO62bcJAAymOS/lQLw1hh1LnqJWbYJ3IrL9gRVtV+DqX3\n\
+            RYfTXrb8O4hAloR/m+UYl6DN/oTdq2QQ+hzhLcoAso67hLTw/nHkneK4TUoIgOxHeAmOOF\n\
+            MUTTqv4G63zibR9JK5KPJLiorVgL4e/mpXh2/KCnmF+HOs+4asszs+gE5EMlJwThtU4aBG\n\
+            rf0konCT4R4rR2kpfLIqd6kMW1q7bxP7AODt4fYv8kWIphr5+VuOnmiz+Xv1uHd53iASBj\n\
+            BWqjx59ihR5CdRd1CDg98ROeGI\n\
+            -----END SSH SIGNATURE-----\n\
+            ";
+
+        let public_key = typed_key(public_key, KeyType::Rsa, Scheme::default());
+        assert!(verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+        assert!(!verify_ssh(&public_key, signature, &CHALLENGE[1..]).unwrap());
+    }
+
+    // noinspection SpellCheckingInspection
+    #[test]
+    fn test_verify_ssh_ecdsa_p256() {
+        let public_key = "\
+            ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDuEmENpHAoQNymX9+fPn\
+            NqBF12spAt5twQ77nHLsnroJoBw2d5DloPGk9I4luWD8aWyuO4rLITGN8iNHtAjGn0= test\
+            ";
+        let signature = "\
+            -----BEGIN SSH SIGNATURE-----\n\
+            U1NIU0lHAAAAAQAAAGgAAAATZWNkc2Etc2hhMi1uaXN0cDI1NgAAAAhuaXN0cDI1NgAAAE\n\
+            EEO4SYQ2kcChA3KZf358+c2oEXXaykC3m3BDvuccuyeugmgHDZ3kOWg8aT0jiW5YPxpbK4\n\
+            7isshMY3yI0e0CMafQAAAAZjbG91ZHkAAAAAAAAABnNoYTUxMgAAAGQAAAATZWNkc2Etc2\n\
+            hhMi1uaXN0cDI1NgAAAEkAAAAhAKTomOEoA7x+02IVgNh2aBX/HvgLlyFzA27GjwPBoTxk\n\
+            AAAAIDu2ANLp2t3ouwoFXoRVA4wRU7cimwL1g93P7UtwKT3Q\n\
+            -----END SSH SIGNATURE-----\n\
+            ";
+
+        let public_key = typed_key(public_key, KeyType::EcdsaP256, Scheme::default());
+        assert!(verify_ssh(&public_key, signature, CHALLENGE).unwrap());
+        assert!(!verify_ssh(&public_key, signature, &CHALLENGE[1..]).unwrap());
+    }
+
+}
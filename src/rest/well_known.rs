@@ -0,0 +1,86 @@
+use jsonwebtoken::{jwk::JwkSet, Algorithm};
+use rocket::{serde::json::Json, Route};
+use serde::Serialize;
+
+use crate::state::JsonWebTokenState;
+
+pub const MOUNT_POINT: &str = "/.well-known";
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        // GET /.well-known/jwks.json
+        jwks,
+        // GET /.well-known/openid-configuration
+        openid_configuration,
+    ]
+}
+
+#[derive(Serialize)]
+struct DiscoveryDocument {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issuer: Option<String>,
+    jwks_uri: String,
+    id_token_signing_alg_values_supported: Vec<Algorithm>,
+}
+
+/**
+ * Public keys verifying the JWTs, for downstream services to verify them on their own.
+ * Symmetric keys are never published, leaving the set empty if only secrets are configured.
+ *
+ * Request:
+ * ```text
+ * GET /.well-known/jwks.json HTTP/<HTTP-Version>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "keys": [
+ *         { "kty": "RSA", "use": "sig", "alg": "RS256", "kid": "<Key-Id>", "n": "<Modulus>", "e": "<Exponent>" },
+ *         ...
+ *     ]
+ * }
+ * ```
+ **/
+#[get("/jwks.json")]
+fn jwks(jsonwebtoken: &JsonWebTokenState) -> Json<JwkSet> {
+    Json(jsonwebtoken.jwk_set())
+}
+
+/**
+ * Minimal discovery document in the form of OpenID Connect Discovery 1.0.
+ *
+ * Request:
+ * ```text
+ * GET /.well-known/openid-configuration HTTP/<HTTP-Version>
+ * ```
+ *
+ * Successful Response:
+ * ```text
+ * HTTP/<HTTP-Version> 200 OK
+ * Content-Type: application/json
+ * Content-Length: <Length-of-Body>
+ *
+ * {
+ *     "issuer": "<Issuer>",
+ *     "jwks_uri": "<Issuer>/.well-known/jwks.json",
+ *     "id_token_signing_alg_values_supported": ["RS256", ...]
+ * }
+ * ```
+ * Where [issuer] is omitted and [jwks_uri] is relative if "jwt.issuer" is not configured,
+ * and [id_token_signing_alg_values_supported] lists no HMAC algorithms, even if the tokens are signed by them.
+ **/
+#[get("/openid-configuration")]
+fn openid_configuration(jsonwebtoken: &JsonWebTokenState) -> Json<DiscoveryDocument> {
+    let issuer = jsonwebtoken.issuer();
+    let jwks_uri = format!("{}{MOUNT_POINT}/jwks.json", issuer.unwrap_or_default());
+    Json(DiscoveryDocument {
+        issuer: issuer.map(str::to_string),
+        jwks_uri,
+        id_token_signing_alg_values_supported: jsonwebtoken.algorithms(),
+    })
+}
@@ -1,7 +1,10 @@
 use rocket::State;
 
-mod authorization;
-pub use authorization::Authorization;
+pub mod authorization;
+pub use authorization::{scope, AdminAuthorization, Authorization, RequireScope};
+
+mod client;
+pub use client::Client;
 
 mod config;
 pub use config::Config;
@@ -13,4 +16,10 @@ pub type DatabaseState = State<Database>;
 
 mod jsonwebtoken;
 pub use jsonwebtoken::JsonWebToken;
-pub type JsonWebTokenState = State<JsonWebToken>;
\ No newline at end of file
+pub type JsonWebTokenState = State<JsonWebToken>;
+
+mod mailer;
+pub use mailer::Mailer;
+pub type MailerState = State<Option<Mailer>>;
+
+pub mod secret;
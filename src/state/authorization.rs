@@ -1,12 +1,15 @@
+use chrono::Utc;
+use mongodb::bson::{doc, Document};
 use rocket::{
     http::Status,
     Request,
     request::{FromRequest, Outcome},
 };
-use serde::Serialize;
 
 use super::{
-    database::collection::{Account, Token},
+    database::collection::{AccessToken, Account, Token},
+    secret,
+    Client,
     Database,
     JsonWebToken,
 };
@@ -19,24 +22,94 @@ mod filter;
 mod find_collections;
 use find_collections::FindCollections;
 
+pub mod scope;
+pub use scope::RequireScope;
+
+mod admin;
+pub use admin::AdminAuthorization;
+
+// 1 minute
+const LAST_SEEN_INTERVAL: i64 = 60 * 1000;
+
+// 5 minutes
+const RECENT_LOGIN_INTERVAL: i64 = 5 * 60 * 1000;
+
+const BEARER_SCHEME: &str = "Bearer";
+
 pub struct Authorization {
-    pub token: Token,
+    pub credential: Credential,
     pub account: Account,
 }
 
+/**
+ * Credential presented in the "Authorization" header,
+ * either a JWT of a session or a personal access token, with or without the "Bearer" scheme.
+ **/
+pub enum Credential {
+    Session(Token),
+    AccessToken(AccessToken),
+}
+
+impl Authorization {
+
+    /**
+     * Token of the session, none if authorized by a personal access token.
+     **/
+    pub fn session(&self) -> Option<&Token> {
+        match &self.credential {
+            Credential::Session(token) => Some(token),
+            Credential::AccessToken(_) => None,
+        }
+    }
+
+    /**
+     * Scopes granted by the credential, full access if none.
+     **/
+    pub fn scope(&self) -> Option<&[String]> {
+        match &self.credential {
+            Credential::Session(token) => token.scope.as_deref(),
+            Credential::AccessToken(access_token) => access_token.scope.as_deref(),
+        }
+    }
+
+    /**
+     * Whether the credential is a session logged in within [RECENT_LOGIN_INTERVAL],
+     * as required for sensitive changes. Refreshing a session does not renew its login,
+     * and personal access tokens never count.
+     **/
+    pub fn is_recent_login(&self) -> bool {
+        self.session().is_some_and(|token| {
+            let login_timestamp = token.family_id().timestamp().timestamp_millis();
+            Utc::now().timestamp_millis() - login_timestamp <= RECENT_LOGIN_INTERVAL
+        })
+    }
+
+}
+
 #[async_trait]
 impl<'r> FromRequest<'r> for Authorization {
     type Error = ();
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let jsonwebtoken = request.rocket().state::<JsonWebToken>().unwrap();
-        let database = request.rocket().state::<Database>().unwrap();
-
-        let Some(authorization) = request.authorization() else {
+        let Some(credential) = request.authorization() else {
             return Outcome::Error((Status::BadRequest, ()))
         };
 
-        let Ok(claims) = jsonwebtoken.decode_jwt(&authorization) else {
+        if credential.starts_with(AccessToken::PREFIX) {
+            Self::of_access_token(request, &credential).await
+        } else {
+            Self::of_session(request, &credential).await
+        }
+    }
+}
+
+impl Authorization {
+
+    async fn of_session(request: &Request<'_>, jwt: &str) -> Outcome<Self, ()> {
+        let jsonwebtoken = request.rocket().state::<JsonWebToken>().unwrap();
+        let database = request.rocket().state::<Database>().unwrap();
+
+        let Ok(claims) = jsonwebtoken.decode_jwt(jwt) else {
             return Outcome::Error((Status::Unauthorized, ()))
         };
         let Ok((token_id, account_id)) = claims.token_and_account() else {
@@ -55,8 +128,92 @@ impl<'r> FromRequest<'r> for Authorization {
             return Outcome::Error((Status::Unauthorized, ()))
         };
 
-        Outcome::Success(Self { token, account })
+        // Reject tokens logged out or revoked
+        if !token.state.is_normal() {
+            return Outcome::Error((Status::Unauthorized, ()))
+        }
+
+        let mut token = token;
+        let now_timestamp = Utc::now().timestamp_millis();
+        // Record the latest use of the token, at most once per interval
+        if token.last_seen.is_none_or(|last_seen| now_timestamp - last_seen >= LAST_SEEN_INTERVAL) {
+            let client = Client::of_request(request);
+            let filter = doc! { "_id": token.id };
+            let update = doc! {
+                "$set": last_seen_of(&client, now_timestamp),
+            };
+            // Failure of recording does not fail the authorization
+            let _ = database.collections.token.update_one(filter, update).await;
+            token.user_agent = client.user_agent.or(token.user_agent);
+            token.ip = client.ip.or(token.ip);
+            token.last_seen = Some(now_timestamp);
+        }
+
+        Outcome::Success(Self { credential: Credential::Session(token), account })
+    }
+
+    async fn of_access_token(request: &Request<'_>, secret: &str) -> Outcome<Self, ()> {
+        let database = request.rocket().state::<Database>().unwrap();
+        let now_timestamp = Utc::now().timestamp_millis();
+
+        // Normal state is not serialized
+        let filter = doc! {
+            "hash": secret::hash(secret),
+            "state": { "$exists": false },
+            "$or": [
+                { "expiry": { "$exists": false } },
+                { "expiry": { "$gt": now_timestamp } },
+            ],
+        };
+        let Ok(access_token) = database.collections.access_token.find_one(filter).await else {
+            return Outcome::Error((Status::InternalServerError, ()))
+        };
+        let Some(mut access_token) = access_token else {
+            return Outcome::Error((Status::Unauthorized, ()))
+        };
+
+        let filter = doc! {
+            "_id": access_token.account,
+            "disabled": { "$exists": false },
+        };
+        let Ok(account) = database.collections.account.find_one(filter).await else {
+            return Outcome::Error((Status::InternalServerError, ()))
+        };
+        let Some(account) = account else {
+            return Outcome::Error((Status::Unauthorized, ()))
+        };
+
+        // Record the latest use of the token, at most once per interval
+        if access_token.last_seen.is_none_or(|last_seen| now_timestamp - last_seen >= LAST_SEEN_INTERVAL) {
+            let filter = doc! { "_id": access_token.id };
+            let update = doc! {
+                "$set": { "last_seen": now_timestamp },
+            };
+            // Failure of recording does not fail the authorization
+            let _ = database.collections.access_token.update_one(filter, update).await;
+            access_token.last_seen = Some(now_timestamp);
+        }
+
+        Outcome::Success(Self { credential: Credential::AccessToken(access_token), account })
     }
+
+}
+
+/**
+ * Fields of a session to set on its use at [now_timestamp],
+ * keeping the recorded device metadata that the [client] does not present.
+ **/
+fn last_seen_of(client: &Client, now_timestamp: i64) -> Document {
+    let mut fields = doc! {
+        "last_seen": now_timestamp,
+    };
+    if let Some(user_agent) = &client.user_agent {
+        fields.insert("user_agent", user_agent);
+    }
+    if let Some(ip) = &client.ip {
+        fields.insert("ip", ip);
+    }
+    fields
 }
 
 trait AuthorizationHeader {
@@ -64,10 +221,99 @@ trait AuthorizationHeader {
 }
 
 impl AuthorizationHeader for Request<'_> {
+    /**
+     * Credential of the header, with the optional "Bearer" scheme stripped.
+     **/
     fn authorization(&self) -> Option<String> {
         self.headers()
             .get_one("Authorization")
-            .map(&str::to_string)
+            .map(|authorization| credential_of(authorization).to_string())
+    }
+}
+
+/**
+ * Credential of the header value [authorization], either after the case-insensitive "Bearer" scheme,
+ * or the whole value for the bare JWTs of the clients predating the scheme.
+ **/
+fn credential_of(authorization: &str) -> &str {
+    let authorization = authorization.trim();
+    match authorization.split_once(char::is_whitespace) {
+        Some((scheme, credential)) if scheme.eq_ignore_ascii_case(BEARER_SCHEME) => credential.trim_start(),
+        _ => authorization,
     }
 }
 
+#[cfg(test)]
+mod test {
+    use mongodb::bson::oid::ObjectId;
+
+    use crate::state::database::collection::{account::Role, token::Issuer};
+
+    use super::*;
+
+    fn account() -> Account {
+        Account::new(ObjectId::new(), "user".into(), Vec::new(), None, Vec::new())
+    }
+
+    /**
+     * Session of a new account of [role], granting [scope].
+     **/
+    pub(super) fn authorization(role: Role, scope: Option<&[&str]>) -> Authorization {
+        let mut account = account();
+        account.role = role;
+        let mut token = Token::new(ObjectId::new(), account.id, 0, Issuer::Password);
+        token.scope = scope.map(|scope| scope.iter().map(|scope| scope.to_string()).collect());
+        Authorization { credential: Credential::Session(token), account }
+    }
+
+    fn session_logged_in_before(seconds: i64) -> Authorization {
+        let login_id = ObjectId::from_parts((Utc::now().timestamp() - seconds) as u32, [0; 5], [0; 3]);
+        let account = account();
+        let mut token = Token::new(ObjectId::new(), account.id, 0, Issuer::Password);
+        token.family = Some(login_id);
+        Authorization { credential: Credential::Session(token), account }
+    }
+
+    #[test]
+    fn test_last_seen_of() {
+        let client = Client { user_agent: None, ip: None };
+        assert_eq!(last_seen_of(&client, 1000), doc! { "last_seen": 1000_i64 });
+
+        let client = Client { user_agent: Some("curl/8.0".into()), ip: None };
+        assert_eq!(last_seen_of(&client, 1000), doc! { "last_seen": 1000_i64, "user_agent": "curl/8.0" });
+
+        let client = Client { user_agent: Some("curl/8.0".into()), ip: Some("127.0.0.1".into()) };
+        assert_eq!(
+            last_seen_of(&client, 1000),
+            doc! { "last_seen": 1000_i64, "user_agent": "curl/8.0", "ip": "127.0.0.1" },
+        );
+    }
+
+    #[test]
+    fn test_credential_of() {
+        assert_eq!(credential_of("Bearer abc.def.ghi"), "abc.def.ghi");
+        assert_eq!(credential_of("bearer abc.def.ghi"), "abc.def.ghi");
+        assert_eq!(credential_of("BEARER cloudy_pat_abc"), "cloudy_pat_abc");
+        assert_eq!(credential_of("  Bearer \t  abc.def.ghi  "), "abc.def.ghi");
+        assert_eq!(credential_of("Bearer\tabc.def.ghi"), "abc.def.ghi");
+        // Bare JWTs of the clients predating the scheme
+        assert_eq!(credential_of("abc.def.ghi"), "abc.def.ghi");
+        assert_eq!(credential_of(" abc.def.ghi\n"), "abc.def.ghi");
+        // Other schemes are not stripped, failing as credentials
+        assert_eq!(credential_of("Basic dXNlcjpwYXNz"), "Basic dXNlcjpwYXNz");
+        assert_eq!(credential_of("Bearerabc"), "Bearerabc");
+    }
+
+    #[test]
+    fn test_is_recent_login() {
+        assert!(session_logged_in_before(0).is_recent_login());
+        assert!(session_logged_in_before(60).is_recent_login());
+        // Refreshed just now, logged in an hour ago
+        assert!(!session_logged_in_before(60 * 60).is_recent_login());
+
+        let account = account();
+        let access_token = AccessToken::new(account.id, "CI".into(), "<Hash>".into(), None, None);
+        let authorization = Authorization { credential: Credential::AccessToken(access_token), account };
+        assert!(!authorization.is_recent_login());
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Deref;
+
+use rocket::{
+    http::Status,
+    Request,
+    request::{FromRequest, Outcome},
+};
+
+use crate::state::database::collection::account::Role;
+
+use super::{scope, Authorization, RequireScope};
+
+/**
+ * [Authorization] of an account of [Role::Admin], by a credential granting the "admin" scope.
+ * Responded with 403 Forbidden otherwise.
+ **/
+pub struct AdminAuthorization {
+    authorization: Authorization,
+}
+
+impl AdminAuthorization {
+    /**
+     * [authorization] granting the "admin" scope if its account is an admin.
+     **/
+    fn of(authorization: RequireScope<scope::Admin>) -> Option<Self> {
+        let authorization = authorization.into_inner();
+        if authorization.account.role != Role::Admin {
+            return None;
+        }
+
+        Some(Self { authorization })
+    }
+}
+
+impl Deref for AdminAuthorization {
+    type Target = Authorization;
+
+    fn deref(&self) -> &Self::Target {
+        &self.authorization
+    }
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for AdminAuthorization {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let authorization = match request.guard::<RequireScope<scope::Admin>>().await {
+            Outcome::Success(authorization) => authorization,
+            Outcome::Error(error) => return Outcome::Error(error),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        match Self::of(authorization) {
+            Some(admin_authorization) => Outcome::Success(admin_authorization),
+            None => Outcome::Error((Status::Forbidden, ())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test::authorization, *};
+
+    fn admin_authorization(role: Role, scope: Option<&[&str]>) -> Option<AdminAuthorization> {
+        RequireScope::<scope::Admin>::of(authorization(role, scope))
+            .and_then(AdminAuthorization::of)
+    }
+
+    #[test]
+    fn test_admin_authorization() {
+        assert!(admin_authorization(Role::Admin, None).is_some());
+        assert!(admin_authorization(Role::Admin, Some(&["admin", "account:read"])).is_some());
+        assert!(admin_authorization(Role::Admin, Some(&["account:write"])).is_none());
+        assert!(admin_authorization(Role::User, None).is_none());
+        assert!(admin_authorization(Role::User, Some(&["admin"])).is_none());
+        assert!(admin_authorization(Role::Readonly, None).is_none());
+    }
+}
@@ -12,6 +12,6 @@ impl ClaimsObjectIds for Claims {
     type R = Result<(ObjectId, ObjectId), Error>;
 
     fn token_and_account(&self) -> Self::R {
-        Ok((ObjectId::parse_str(&self.account)?, ObjectId::parse_str(&self.id)?))
+        Ok((ObjectId::parse_str(&self.id)?, ObjectId::parse_str(&self.account)?))
     }
-}
\ No newline at end of file
+}
@@ -18,9 +18,11 @@ impl FindCollections for Database {
 
     async fn find_account_token(&self, filter_documents: (Document, Document)) -> Self::R {
         let (token_filter, account_filter) = filter_documents;
-        try_join!(
+        let (token, account) = try_join!(
             self.collections.token.find_one(token_filter),
             self.collections.account.find_one(account_filter)
-        )
+        )?;
+        // Disabled accounts are not found, failing the authorization of all their tokens
+        Ok((token, account.filter(Account::is_enabled)))
     }
 }
\ No newline at end of file
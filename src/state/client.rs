@@ -0,0 +1,37 @@
+use std::convert::Infallible;
+
+use rocket::{
+    Request,
+    request::{FromRequest, Outcome},
+};
+
+/**
+ * Device metadata of the requesting client, recorded on its [super::database::collection::Token].
+ **/
+pub struct Client {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Client {
+
+    pub fn of_request(request: &Request<'_>) -> Self {
+        let user_agent = request.headers()
+            .get_one("User-Agent")
+            .map(&str::to_string);
+        let ip = request.client_ip()
+            .map(|ip| ip.to_string());
+
+        Self { user_agent, ip }
+    }
+
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for Client {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Client::of_request(request))
+    }
+}
@@ -25,6 +25,18 @@ impl Config {
         Self { key_value_map }
     }
 
+    /**
+     * Config of only the [pairs] of dotted keys and values, for tests.
+     **/
+    #[cfg(test)]
+    pub fn of_pairs(pairs: &[(&str, &str)]) -> Self {
+        let key_value_map = pairs.iter()
+            .map(|(key, value)| (key.to_lowercase(), value.to_string()))
+            .collect();
+
+        Self { key_value_map }
+    }
+
     fn process_index(index: Vec<String>) -> String {
         index.iter()
             .map(|schema| schema.to_lowercase())
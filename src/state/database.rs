@@ -1,3 +1,9 @@
+use mongodb::{
+    bson::doc,
+    error::{Error, ErrorKind, Result, WriteFailure},
+};
+use rocket::fairing::AdHoc;
+
 use super::Config;
 
 mod connector;
@@ -8,7 +14,8 @@ use metadata::Metadata;
 pub mod collection;
 
 mod collections;
-use collections::Collections;
+pub use collections::Collections;
+use collection::Account;
 
 pub struct Database {
     metadata: Metadata,
@@ -29,5 +36,74 @@ impl Database {
         }
     }
 
+    /**
+     * Create the indexes that the collections rely on, idempotent if they already exist.
+     **/
+    pub async fn create_indexes(&self) -> Result<()> {
+        self.collections.create_indexes().await
+    }
+
+    /**
+     * Create the indexes on the ignition of Rocket, without failing it, since existing data
+     * violating a unique index must be fixed by hand while the rest keeps serving.
+     * Failures are logged along with the duplicate usernames blocking the unique index.
+     **/
+    pub fn index_fairing() -> AdHoc {
+        AdHoc::on_ignite("Database Indexes", |rocket| Box::pin(async move {
+            let database = rocket.state::<Database>().unwrap();
+            if let Err(err) = database.create_indexes().await {
+                warn!("Failed to create the database indexes ({}), usernames are not enforced unique.", err);
+                match database.find_duplicate_usernames().await {
+                    Ok(usernames) if !usernames.is_empty() => {
+                        warn!("Duplicate usernames to be renamed or removed: {}.", usernames.join(", "));
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("Failed to look up the duplicate usernames ({}).", err),
+                }
+            }
+            rocket
+        }))
+    }
+
+    /**
+     * Usernames shared by more than one account, which block the unique username index.
+     **/
+    async fn find_duplicate_usernames(&self) -> Result<Vec<String>> {
+        let pipeline = [
+            doc! { "$group": { "_id": "$username", "count": { "$sum": 1 } } },
+            doc! { "$match": { "count": { "$gt": 1 } } },
+        ];
+        let mut cursor = self.collections.account.aggregate(pipeline).await?;
+        let mut usernames = Vec::new();
+        while cursor.advance().await? {
+            if let Ok(username) = cursor.current().get_str("_id") {
+                usernames.push(username.to_string());
+            }
+        }
+        Ok(usernames)
+    }
+
+    /**
+     * Account of [username], the lookup shared by every login and username check.
+     * Usernames are only validated on registration and change, so that accounts
+     * created before the rules existed can still log in.
+     **/
+    pub async fn find_account_by_username(&self, username: &str) -> Result<Option<Account>> {
+        let filter = doc! { "username": username };
+        self.collections.account.find_one(filter).await
+    }
+
 }
 
+// Server error code of unique index violations
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/**
+ * Whether [error] is caused by a document violating a unique index.
+ **/
+pub fn is_duplicate_key(error: &Error) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == DUPLICATE_KEY_CODE
+    )
+}
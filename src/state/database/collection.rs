@@ -3,3 +3,18 @@ pub use account::Account;
 
 pub mod token;
 pub use token::Token;
+
+mod access_token;
+pub use access_token::AccessToken;
+
+mod challenge;
+pub use challenge::Challenge;
+
+mod invitation;
+pub use invitation::Invitation;
+
+pub mod mail_token;
+pub use mail_token::MailToken;
+
+mod mfa_ticket;
+pub use mfa_ticket::MfaTicket;
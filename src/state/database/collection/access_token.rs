@@ -0,0 +1,66 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::token::State;
+
+/**
+ * Long-lived personal access token for automation, presented as `Bearer cloudy_pat_<Secret>`
+ * and stored as its hash only.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct AccessToken {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub account: ObjectId,
+    pub name: String,
+    pub hash: String,
+    /**
+     * Scopes granted by the token, full access if not specified.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
+    /**
+     * Timestamp in milliseconds, never expiring if not specified.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<i64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "State::is_normal")]
+    pub state: State,
+    /**
+     * Timestamp in milliseconds of the latest authorized request.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<i64>,
+}
+
+impl AccessToken {
+
+    /**
+     * Prefix of presented tokens, telling them apart from JWTs and making leaked ones easy to scan for.
+     **/
+    pub const PREFIX: &'static str = "cloudy_pat_";
+
+    pub fn new(
+        account: ObjectId,
+        name: String,
+        hash: String,
+        scope: Option<Vec<String>>,
+        expiry: Option<i64>,
+    ) -> Self {
+        Self {
+            id: ObjectId::new(),
+            account,
+            name,
+            hash,
+            scope,
+            expiry,
+            state: State::Normal,
+            last_seen: None,
+        }
+    }
+
+}
@@ -7,6 +7,9 @@ pub use public_key::PublicKey;
 mod onetime_password_secret;
 pub use onetime_password_secret::OnetimePasswordSecret;
 
+mod role;
+pub use role::Role;
+
 #[derive(Serialize, Deserialize)]
 pub struct Account {
     #[serde(rename = "_id")]
@@ -16,9 +19,123 @@ pub struct Account {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub onetime_password_secret: Option<OnetimePasswordSecret>,
-    // TODO: To be implemented
+    /**
+     * Hashes of the unused recovery codes, generated at the enrollment of one-time password.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recovery_codes: Vec<String>,
+    /**
+     * PHC string of the Argon2id hash of the password, if one has been set.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /**
+     * Whether logins have to be completed with a one-time password after the first factor.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub mfa_required: bool,
+    #[serde(default)]
+    pub role: Role,
+    /**
+     * Timestamp in milliseconds of disabling the account by an admin, absent while enabled.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<i64>,
+    /**
+     * Timestamp in milliseconds after which the account is purged, absent unless its deletion is requested.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion: Option<i64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /**
+     * Timestamp in milliseconds of verifying [email], absent until verified or once [email] is changed.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<i64>,
+    /**
+     * Reference to the avatar image, e.g. a URL or a file id.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    /**
+     * BCP 47 language tag, e.g. "en-US".
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /**
+     * IANA time zone name, e.g. "Europe/London".
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
 }
 
 impl Account {
-    // TODO: To be implemented
-}
\ No newline at end of file
+
+    pub fn new(
+        id: ObjectId,
+        username: String,
+        public_keys: Vec<PublicKey>,
+        onetime_password_secret: Option<OnetimePasswordSecret>,
+        recovery_codes: Vec<String>,
+    ) -> Self {
+        Self {
+            id,
+            username,
+            public_keys,
+            onetime_password_secret,
+            recovery_codes,
+            password: None,
+            mfa_required: false,
+            role: Role::User,
+            disabled: None,
+            deletion: None,
+            display_name: None,
+            email: None,
+            email_verified: None,
+            avatar: None,
+            locale: None,
+            timezone: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.disabled.is_none()
+    }
+
+    /**
+     * Usernames are 3 to 32 characters of ASCII letters, digits, '_', '-' or '.'.
+     **/
+    pub fn is_valid_username(username: &str) -> bool {
+        (3..=32).contains(&username.len())
+            && username.chars().all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '-' | '.'))
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_username() {
+        assert!(Account::is_valid_username("user_01"));
+        assert!(Account::is_valid_username("first.last-name"));
+        assert!(!Account::is_valid_username("ab"));
+        assert!(!Account::is_valid_username("user name"));
+        assert!(!Account::is_valid_username("user@example.com"));
+    }
+}
@@ -2,6 +2,43 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct OnetimePasswordSecret {
-    pub issue: i64,
+    /**
+     * Timestamp of the confirmed enrollment,
+     * absent while the secret is pending for confirmation with a first valid code.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<i64>,
     pub secret: String,
-}
\ No newline at end of file
+    /**
+     * Time step of the last accepted one-time password,
+     * codes of the same or earlier steps are rejected as replays.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_step: Option<i64>,
+    /**
+     * Hash of the registration secret, required to confirm a secret enrolled at registration
+     * without a session, removed on confirmation.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration: Option<String>,
+}
+
+impl OnetimePasswordSecret {
+
+    pub fn pending(secret: String) -> Self {
+        Self {
+            issue: None,
+            secret,
+            last_step: None,
+            registration: None,
+        }
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.issue.is_some()
+    }
+
+}
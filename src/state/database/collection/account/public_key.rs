@@ -7,12 +7,72 @@ pub struct PublicKey {
     pub id: ObjectId,
     pub key: String,
     pub validity: Validity,
+    #[serde(default)]
+    pub key_type: KeyType,
+    #[serde(default)]
+    pub scheme: Scheme,
 }
 
-#[derive(Serialize, Deserialize)]
+impl PublicKey {
+
+    pub fn new(key: String, validity: Validity, key_type: KeyType, scheme: Scheme) -> Self {
+        Self {
+            id: ObjectId::new(),
+            key,
+            validity,
+            key_type,
+            scheme,
+        }
+    }
+
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
 pub enum Validity {
     Master,
     Permanent,
     Temporary(i64),
     Disabled(i64),
-}
\ No newline at end of file
+}
+
+/**
+ * Algorithm family of [PublicKey::key],
+ * stored as either a PEM "PUBLIC KEY" or an OpenSSH `authorized_keys` line.
+ **/
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    #[default]
+    Rsa,
+    Ed25519,
+    /**
+     * ECDSA over NIST P-256 with SHA-256.
+     **/
+    EcdsaP256,
+    /**
+     * ECDSA over NIST P-384 with SHA-384.
+     **/
+    EcdsaP384,
+}
+
+/**
+ * Signature scheme of RSA keys for verifying the challenges signed with the key,
+ * not applicable to other [KeyType]s.
+ **/
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub enum Scheme {
+    /**
+     * Legacy: the message itself is PKCS#1 padded and transformed with the private key,
+     * without any digest, i.e. `openssl pkeyutl -sign`.
+     **/
+    #[default]
+    RsaRaw,
+    /**
+     * RSASSA-PKCS1-v1_5 with SHA-256, i.e. `openssl dgst -sha256 -sign` or WebCrypto "RSASSA-PKCS1-v1_5".
+     **/
+    RsaPkcs1Sha256,
+    /**
+     * RSASSA-PSS with SHA-256 and MGF1 with SHA-256, any salt length,
+     * i.e. WebCrypto "RSA-PSS".
+     **/
+    RsaPssSha256,
+}
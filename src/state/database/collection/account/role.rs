@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/**
+ * Role of an [super::Account], deciding what its credentials can be granted beyond the token scopes.
+ **/
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum Role {
+    /**
+     * Full access to the account, and to the admin API.
+     **/
+    Admin,
+    /**
+     * Full access to the account.
+     **/
+    #[default]
+    User,
+    /**
+     * Reading scopes only, whatever the token grants.
+     **/
+    Readonly,
+}
@@ -0,0 +1,47 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/**
+ * Server-issued nonce to be signed for signature login, consumed on verification.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct Challenge {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub username: String,
+    pub nonce: String,
+    /**
+     * Date, after which the challenge is removed by the TTL index.
+     **/
+    pub expiry: DateTime,
+}
+
+impl Challenge {
+
+    pub fn new(username: String, nonce: String, expiry: DateTime) -> Self {
+        Self {
+            id: ObjectId::new(),
+            username,
+            nonce,
+            expiry,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use mongodb::bson::{self, Bson};
+
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        let expiry = DateTime::from_millis(1_700_000_000_000);
+        let challenge = Challenge::new("user".into(), "nonce".into(), expiry);
+        let document = bson::to_document(&challenge).unwrap();
+
+        // Matched by the TTL index on the expiry
+        assert_eq!(document.get("expiry"), Some(&Bson::DateTime(expiry)));
+    }
+}
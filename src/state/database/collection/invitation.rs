@@ -0,0 +1,40 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/**
+ * Single-use code for registering an account, issued by an admin and stored as its hash only.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct Invitation {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub hash: String,
+    /**
+     * Account issuing the invitation.
+     **/
+    pub issuer: ObjectId,
+    /**
+     * Timestamp in milliseconds.
+     **/
+    pub expiry: i64,
+    /**
+     * Account registered with the invitation, absent while it is unused.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redeemer: Option<ObjectId>,
+}
+
+impl Invitation {
+
+    pub fn new(hash: String, issuer: ObjectId, expiry: i64) -> Self {
+        Self {
+            id: ObjectId::new(),
+            hash,
+            issuer,
+            expiry,
+            redeemer: None,
+        }
+    }
+
+}
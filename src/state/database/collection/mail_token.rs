@@ -0,0 +1,69 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/**
+ * Single-use token sent by mail as a link, stored as its hash only.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct MailToken {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub account: ObjectId,
+    pub hash: String,
+    pub purpose: Purpose,
+    /**
+     * Date, after which the token is removed by the TTL index.
+     **/
+    pub expiry: DateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Purpose {
+    /**
+     * Verify that the account owns [email], the address the token is sent to.
+     **/
+    EmailVerification {
+        email: String,
+    },
+    /**
+     * Log in to the account, with the scopes requested along with the link.
+     **/
+    MagicLink {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        scope: Option<Vec<String>>,
+    },
+}
+
+impl MailToken {
+
+    pub fn new(account: ObjectId, hash: String, purpose: Purpose, expiry: DateTime) -> Self {
+        Self {
+            id: ObjectId::new(),
+            account,
+            hash,
+            purpose,
+            expiry,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use mongodb::bson::{self, Bson};
+
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        let expiry = DateTime::from_millis(1_700_000_000_000);
+        let purpose = Purpose::MagicLink { scope: None };
+        let mail_token = MailToken::new(ObjectId::new(), "hash".into(), purpose, expiry);
+        let document = bson::to_document(&mail_token).unwrap();
+
+        // Matched by the filters on the purpose, and by the TTL index on the expiry
+        assert!(document.get_document("purpose").unwrap().contains_key("MagicLink"));
+        assert_eq!(document.get("expiry"), Some(&Bson::DateTime(expiry)));
+    }
+}
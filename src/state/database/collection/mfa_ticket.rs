@@ -0,0 +1,67 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use super::token::Issuer;
+
+/**
+ * Pending login of an account requiring multi-factor authentication,
+ * issued after the first factor and consumed by the second one.
+ * Only the hash of the ticket is stored.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct MfaTicket {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub account: ObjectId,
+    pub hash: String,
+    /**
+     * Issuer of the first factor.
+     **/
+    pub issuer: Issuer,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
+    /**
+     * Date, after which the ticket is removed by the TTL index.
+     **/
+    pub expiry: DateTime,
+}
+
+impl MfaTicket {
+
+    pub fn new(
+        account: ObjectId,
+        hash: String,
+        issuer: Issuer,
+        scope: Option<Vec<String>>,
+        expiry: DateTime,
+    ) -> Self {
+        Self {
+            id: ObjectId::new(),
+            account,
+            hash,
+            issuer,
+            scope,
+            expiry,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use mongodb::bson::{self, Bson};
+
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        let expiry = DateTime::from_millis(1_700_000_000_000);
+        let mfa_ticket = MfaTicket::new(ObjectId::new(), "hash".into(), Issuer::MagicLink, None, expiry);
+        let document = bson::to_document(&mfa_ticket).unwrap();
+
+        // Matched by the TTL index on the expiry
+        assert_eq!(document.get("expiry"), Some(&Bson::DateTime(expiry)));
+        assert!(!document.contains_key("scope"));
+    }
+}
@@ -7,6 +7,9 @@ pub use state::State;
 mod issuer;
 pub use issuer::Issuer;
 
+mod refresh;
+pub use refresh::Refresh;
+
 #[derive(Serialize, Deserialize)]
 pub struct Token {
     #[serde(rename = "_id")]
@@ -17,17 +20,51 @@ pub struct Token {
     #[serde(default)]
     #[serde(skip_serializing_if = "State::is_normal")]
     pub state: State,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    /**
+     * Timestamp in milliseconds of issuing or the latest authorized request.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<i64>,
+    /**
+     * Id of the first token issued by the login, shared by all tokens rotated from it.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<ObjectId>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh: Option<Refresh>,
+    /**
+     * Scopes granted by the token, full access if not specified.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
 }
 
 impl State {
-    fn is_normal(&self) -> bool {
+    pub fn is_normal(&self) -> bool {
         matches!(self, Self::Normal)
     }
 }
 
 impl Token {
 
-    fn new(
+    /**
+     * Id of the token family, which is the token itself for tokens issued before refresh tokens.
+     **/
+    pub fn family_id(&self) -> ObjectId {
+        self.family.unwrap_or(self.id)
+    }
+
+    pub fn new(
         id: ObjectId,
         account: ObjectId,
         expiry: i64,
@@ -39,30 +76,13 @@ impl Token {
             issuer,
             expiry,
             state: State::Normal,
+            user_agent: None,
+            ip: None,
+            last_seen: None,
+            family: None,
+            refresh: None,
+            scope: None,
         }
     }
 
-    pub fn of_signature(
-        id: ObjectId,
-        account: ObjectId,
-        public_key: ObjectId,
-        expiry: i64,
-    ) -> Self {
-        Self::new(
-            id,
-            account,
-            expiry,
-            Issuer::PublicKey(public_key),
-        )
-    }
-
-    pub fn of_passkey(id: ObjectId, account: ObjectId, expiry: i64) -> Self {
-        Self::new(
-            id,
-            account,
-            expiry,
-            Issuer::OnetimePassword,
-        )
-    }
-
 }
\ No newline at end of file
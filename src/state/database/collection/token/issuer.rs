@@ -3,6 +3,47 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub enum Issuer {
+    MagicLink,
     OnetimePassword,
+    Password,
     PublicKey(ObjectId),
-}
\ No newline at end of file
+    RecoveryCode,
+    /**
+     * Login completed by a [second] factor after the [first] one,
+     * for accounts requiring multi-factor authentication.
+     **/
+    MultiFactor {
+        first: Box<Issuer>,
+        second: Box<Issuer>,
+    },
+}
+
+impl Issuer {
+
+    /**
+     * The issuer itself, or the first factor of a multi-factor login.
+     **/
+    pub fn first_factor(&self) -> &Issuer {
+        match self {
+            Self::MultiFactor { first, .. } => first,
+            issuer => issuer,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_first_factor() {
+        assert!(matches!(Issuer::Password.first_factor(), Issuer::Password));
+        let public_key_id = ObjectId::new();
+        let issuer = Issuer::MultiFactor {
+            first: Box::new(Issuer::PublicKey(public_key_id)),
+            second: Box::new(Issuer::RecoveryCode),
+        };
+        assert!(matches!(issuer.first_factor(), Issuer::PublicKey(id) if *id == public_key_id));
+    }
+}
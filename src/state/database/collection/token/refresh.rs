@@ -0,0 +1,13 @@
+use rocket::serde::{Deserialize, Serialize};
+
+/**
+ * Opaque refresh token of a [super::Token], stored as its hash only.
+ **/
+#[derive(Serialize, Deserialize)]
+pub struct Refresh {
+    pub hash: String,
+    /**
+     * Timestamp in milliseconds.
+     **/
+    pub expiry: i64,
+}
@@ -1,13 +1,12 @@
 use rocket::serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub enum State {
+    #[default]
     Normal,
     Disabled(i64),
+    /**
+     * Replaced by a new token of the same family through its refresh token.
+     **/
+    Rotated(i64),
 }
-
-impl Default for State {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
\ No newline at end of file
@@ -1,10 +1,25 @@
-use mongodb::{Collection, Database};
+use std::time::Duration;
 
-use super::collection::{Account, Token};
+use mongodb::{
+    bson::doc,
+    error::Result,
+    options::IndexOptions,
+    Collection,
+    Database,
+    IndexModel,
+};
 
+use super::collection::{AccessToken, Account, Challenge, Invitation, MailToken, MfaTicket, Token};
+
+#[derive(Clone)]
 pub struct Collections {
     pub account: Collection<Account>,
     pub token: Collection<Token>,
+    pub access_token: Collection<AccessToken>,
+    pub challenge: Collection<Challenge>,
+    pub mfa_ticket: Collection<MfaTicket>,
+    pub invitation: Collection<Invitation>,
+    pub mail_token: Collection<MailToken>,
 }
 
 impl Collections {
@@ -13,12 +28,46 @@ impl Collections {
         Self {
             account: database.collection(collection_name::ACCOUNT),
             token: database.collection(collection_name::TOKEN),
+            access_token: database.collection(collection_name::ACCESS_TOKEN),
+            challenge: database.collection(collection_name::CHALLENGE),
+            mfa_ticket: database.collection(collection_name::MFA_TICKET),
+            invitation: database.collection(collection_name::INVITATION),
+            mail_token: database.collection(collection_name::MAIL_TOKEN),
         }
     }
 
+    pub async fn create_indexes(&self) -> Result<()> {
+        self.challenge.create_index(expiring()).await?;
+        self.mail_token.create_index(expiring()).await?;
+        self.mfa_ticket.create_index(expiring()).await?;
+
+        let unique_username = IndexModel::builder()
+            .keys(doc! { "username": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.account.create_index(unique_username).await?;
+        Ok(())
+    }
+
+}
+
+/**
+ * TTL index removing the documents once their "expiry" date has passed,
+ * within the minute of the TTL monitor.
+ **/
+fn expiring() -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! { "expiry": 1 })
+        .options(IndexOptions::builder().expire_after(Duration::ZERO).build())
+        .build()
 }
 
 mod collection_name {
     pub const ACCOUNT: &str = "account";
     pub const TOKEN: &str = "token";
+    pub const ACCESS_TOKEN: &str = "access_token";
+    pub const CHALLENGE: &str = "challenge";
+    pub const MFA_TICKET: &str = "mfa_ticket";
+    pub const INVITATION: &str = "invitation";
+    pub const MAIL_TOKEN: &str = "mail_token";
 }
\ No newline at end of file
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc, Duration};
 use jsonwebtoken::{
-    errors::Error,
+    errors::{Error, ErrorKind},
+    jwk::{AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse},
+    Algorithm,
     DecodingKey,
     EncodingKey,
     Header,
@@ -11,14 +16,93 @@ use serde::{Deserialize, Serialize};
 use crate::state::Config;
 
 mod metadata;
-use metadata::{Metadata, Key};
+use metadata::{Metadata, Key, VerificationKey};
+
+mod jwk;
 
 pub struct JsonWebToken {
+    issuer: Option<String>,
+    audience: Option<String>,
     header: Header,
-    validation: Validation,
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    /**
+     * Keys by "kid", including the signing key.
+     **/
+    verifying_keys: HashMap<String, VerifyingKey>,
     duration: Duration,
+    refresh_duration: Duration,
+}
+
+struct VerifyingKey {
+    algorithm: Algorithm,
+    validation: Validation,
+    decoding_key: DecodingKey,
+    /**
+     * Public JWK parameters, none for symmetric keys.
+     **/
+    jwk_parameters: Option<AlgorithmParameters>,
+    retirement: Option<DateTime<Utc>>,
+}
+
+impl VerifyingKey {
+
+    fn of(
+        key_id: &str,
+        algorithm: Algorithm,
+        verification_key: &VerificationKey,
+        retirement: Option<DateTime<Utc>>,
+    ) -> Self {
+        if !verification_key.family().supports(algorithm) {
+            panic!(
+                r#"Panic: JWT algorithm {algorithm:?} does not match the {:?} key "{key_id}"."#,
+                verification_key.family(),
+            );
+        }
+        let (decoding_key, jwk_parameters) = match verification_key {
+            VerificationKey::Secret(secret) => {
+                (DecodingKey::from_secret(secret.as_bytes()), None)
+            }
+            VerificationKey::RsaPEM(public_key) => {
+                (crypto_keys_processor::decoding_key_of_rsa_pem(public_key), Some(jwk::of_rsa_pem(public_key)))
+            }
+            VerificationKey::RsaDER(public_key) => {
+                (crypto_keys_processor::decoding_key_of_rsa_der(public_key), Some(jwk::of_rsa_der(public_key)))
+            }
+            VerificationKey::EcPEM(public_key) => {
+                (crypto_keys_processor::decoding_key_of_ec_pem(public_key), Some(jwk::of_ec_pem(public_key)))
+            }
+            VerificationKey::EcDER(public_key) => {
+                (crypto_keys_processor::decoding_key_of_ec_der(public_key), Some(jwk::of_ec_der(public_key)))
+            }
+            VerificationKey::EdPEM(public_key) => {
+                (crypto_keys_processor::decoding_key_of_ed_pem(public_key), Some(jwk::of_ed_pem(public_key)))
+            }
+            VerificationKey::EdDER(public_key) => {
+                (crypto_keys_processor::decoding_key_of_ed_der(public_key), Some(jwk::of_ed_der(public_key)))
+            }
+        };
+        // ES256 and ES384 are bound to the curves P-256 and P-384
+        if let Some(AlgorithmParameters::EllipticCurve(parameters)) = &jwk_parameters {
+            let curve = match algorithm {
+                Algorithm::ES384 => EllipticCurve::P384,
+                _ => EllipticCurve::P256,
+            };
+            if parameters.curve != curve {
+                panic!(r#"Panic: JWT algorithm {algorithm:?} does not match the curve of EC key "{key_id}"."#);
+            }
+        }
+
+        Self {
+            algorithm,
+            validation: Validation::new(algorithm),
+            decoding_key,
+            jwk_parameters,
+            retirement,
+        }
+    }
+    fn is_retired(&self) -> bool {
+        self.retirement.is_some_and(|retirement| retirement <= Utc::now())
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -27,44 +111,111 @@ pub struct Claims {
     pub id: String,
     #[serde(rename = "sub")]
     pub account: String,
+    #[serde(rename = "iss")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(rename = "aud")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
     #[serde(rename = "iat")]
     pub issue: i64,
+    #[serde(rename = "nbf")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<i64>,
     #[serde(rename = "exp")]
     pub expiry: i64,
+    /**
+     * Granted scopes separated by spaces, full access if not specified.
+     **/
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
 
 impl JsonWebToken {
 
     pub fn from_config(config: &Config) -> Self {
-        let metadata = Metadata::from_config(config);
-
-        let header = metadata.algorithm
-            .map(Header::new)
-            .unwrap_or_else(Header::default);
-        let validation = metadata.algorithm
-            .map(Validation::new)
-            .unwrap_or_else(Validation::default);
-
-        let (encoding_key, decoding_key) = match metadata.key {
-            Key::Secret(secret) => {
-                crypto_keys_processor::of_secret(&secret)
-            }
-            Key::RsaPEM(public_key, private_key) => {
-                crypto_keys_processor::of_rsa_pem(&public_key, &private_key)
-            }
-            Key::RsaDER(public_key, private_key) => {
-                crypto_keys_processor::of_rsa_der(&public_key, &private_key)
-            }
+        Self::from_metadata(Metadata::from_config(config))
+    }
+
+    fn from_metadata(metadata: Metadata) -> Self {
+        let verification_key = metadata.key.verification_key();
+        let key_family = verification_key.family();
+        let algorithm = metadata.algorithm
+            .unwrap_or_else(|| key_family.default_algorithm());
+        let mut header = Header::new(algorithm);
+        header.kid = Some(metadata.key_id.clone());
+
+        let encoding_key = match &metadata.key {
+            Key::Secret(secret) => EncodingKey::from_secret(secret.as_bytes()),
+            Key::RsaPEM(_, private_key) => crypto_keys_processor::encoding_key_of_rsa_pem(private_key),
+            Key::RsaDER(_, private_key) => crypto_keys_processor::encoding_key_of_rsa_der(private_key),
+            Key::EcPEM(_, private_key) => crypto_keys_processor::encoding_key_of_ec_pem(private_key),
+            Key::EcDER(_, private_key) => crypto_keys_processor::encoding_key_of_ec_der(private_key),
+            Key::EdPEM(_, private_key) => crypto_keys_processor::encoding_key_of_ed_pem(private_key),
+            Key::EdDER(_, private_key) => crypto_keys_processor::encoding_key_of_ed_der(private_key),
         };
 
+        let mut verifying_keys = HashMap::new();
+        for retired_key in metadata.retired_keys {
+            let retired_key_family = retired_key.key.family();
+            let algorithm = retired_key.algorithm
+                .or(metadata.algorithm.filter(|algorithm| retired_key_family.supports(*algorithm)))
+                .unwrap_or_else(|| retired_key_family.default_algorithm());
+            let retirement = retired_key.retirement
+                .map(|retirement| {
+                    DateTime::from_timestamp_millis(retirement)
+                        .unwrap_or_else(|| panic!(r#"Panic: Invalid retirement of JWT key "{}"."#, retired_key.id))
+                });
+            let verifying_key = VerifyingKey::of(&retired_key.id, algorithm, &retired_key.key, retirement);
+            verifying_keys.insert(retired_key.id, verifying_key);
+        }
+        // Signing key takes over a retired key of the same id
+        let verifying_key = VerifyingKey::of(&metadata.key_id, algorithm, &verification_key, None);
+        verifying_keys.insert(metadata.key_id.clone(), verifying_key);
+
+        // Tokens of other environments are rejected, even if signed with a shared key
+        for verifying_key in verifying_keys.values_mut() {
+            restrict_validation(
+                &mut verifying_key.validation,
+                metadata.issuer.as_deref(),
+                metadata.audience.as_deref(),
+            );
+        }
+
         let duration = Duration::milliseconds(metadata.duration);
+        let refresh_duration = Duration::milliseconds(metadata.refresh_duration);
 
-        Self {
+        let jsonwebtoken = Self {
+            issuer: metadata.issuer,
+            audience: metadata.audience,
             header,
-            validation,
             encoding_key,
-            decoding_key,
+            verifying_keys,
             duration,
+            refresh_duration,
+        };
+        jsonwebtoken.check_signing_key();
+        jsonwebtoken
+    }
+
+    /**
+     * Sign and verify a probe token, so that mismatches between the algorithm, the private key
+     * and the public key fail at startup instead of on the first request.
+     **/
+    fn check_signing_key(&self) {
+        let claims = self.new_claims("", "", &Utc::now(), None);
+        let is_verified = self.encode_jwt(&claims)
+            .and_then(|jwt| self.decode_jwt(&jwt))
+            .is_ok();
+        if !is_verified {
+            panic!(
+                "Panic: JWT signing key does not match the algorithm {:?} or its public key.",
+                self.header.alg,
+            );
         }
     }
 
@@ -72,17 +223,26 @@ impl JsonWebToken {
         *timestamp + self.duration
     }
 
+    pub fn refresh_expiry_from(&self, timestamp: &DateTime<Utc>) -> DateTime<Utc> {
+        *timestamp + self.refresh_duration
+    }
+
     pub fn new_claims(
         &self,
-        token_id: &String,
-        account_id: &String,
-        issue_timestamp: &DateTime<Utc>
+        token_id: &str,
+        account_id: &str,
+        issue_timestamp: &DateTime<Utc>,
+        scope: Option<&[String]>,
     ) -> Claims {
         Claims {
-            id: token_id.clone(),
-            account: account_id.clone(),
+            id: token_id.to_string(),
+            account: account_id.to_string(),
+            issuer: self.issuer.clone(),
+            audience: self.audience.clone(),
             issue: issue_timestamp.timestamp(),
+            not_before: Some(issue_timestamp.timestamp()),
             expiry: self.expiry_from(issue_timestamp).timestamp(),
+            scope: scope.map(|scope| scope.join(" ")),
         }
     }
 
@@ -90,61 +250,440 @@ impl JsonWebToken {
         jsonwebtoken::encode(&self.header, claims, &self.encoding_key)
     }
 
-    pub fn decode_jwt(&self, jwt_str: &String) -> Result<Claims, Error> {
-        jsonwebtoken::decode::<Claims>(&*jwt_str, &self.decoding_key, &self.validation)
+    /**
+     * Decode with the key of the "kid" in the header, or the signing key
+     * for tokens signed before key ids were introduced.
+     **/
+    pub fn decode_jwt(&self, jwt_str: &str) -> Result<Claims, Error> {
+        let header = jsonwebtoken::decode_header(jwt_str)?;
+        let key_id = header.kid.as_ref()
+            .or(self.header.kid.as_ref())
+            .ok_or(ErrorKind::InvalidToken)?;
+        let verifying_key = self.verifying_keys.get(key_id)
+            .ok_or(ErrorKind::InvalidToken)?;
+        if verifying_key.is_retired() {
+            return Err(ErrorKind::InvalidToken.into());
+        }
+        jsonwebtoken::decode::<Claims>(jwt_str, &verifying_key.decoding_key, &verifying_key.validation)
             // Hide the header, expose the claims only
             .map(|token_data| token_data.claims)
     }
 
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /**
+     * Asymmetric algorithms of the keys still verifying, the signing one first,
+     * leaving out HMAC, whose tokens cannot be verified by anyone without the shared secret.
+     **/
+    pub fn algorithms(&self) -> Vec<Algorithm> {
+        let mut algorithms = Vec::new();
+        let verifying_algorithms = self.verifying_keys.values()
+            .filter(|verifying_key| !verifying_key.is_retired())
+            .map(|verifying_key| verifying_key.algorithm);
+        for algorithm in std::iter::once(self.header.alg).chain(verifying_algorithms) {
+            if !is_hmac(algorithm) && !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        algorithms
+    }
+
+    /**
+     * Public keys still verifying, identified by their "kid".
+     **/
+    pub fn jwk_set(&self) -> JwkSet {
+        let keys = self.verifying_keys.iter()
+            .filter(|(_, verifying_key)| !verifying_key.is_retired())
+            .filter_map(|(key_id, verifying_key)| {
+                let algorithm_parameters = verifying_key.jwk_parameters.clone()?;
+                let common = CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: KeyAlgorithm::from_str(&format!("{:?}", verifying_key.algorithm)).ok(),
+                    key_id: Some(key_id.clone()),
+                    ..Default::default()
+                };
+                Some(Jwk { common, algorithm: algorithm_parameters })
+            })
+            .collect();
+        JwkSet { keys }
+    }
+
+}
+
+/**
+ * Require "iss" and "aud" to be the configured ones if configured, and validate "nbf" if present.
+ **/
+fn restrict_validation(validation: &mut Validation, issuer: Option<&str>, audience: Option<&str>) {
+    let mut required_spec_claims = vec!["exp"];
+    validation.validate_nbf = true;
+    if let Some(issuer) = issuer {
+        validation.set_issuer(&[issuer]);
+        required_spec_claims.push("iss");
+    }
+    if let Some(audience) = audience {
+        validation.set_audience(&[audience]);
+        required_spec_claims.push("aud");
+    }
+    validation.set_required_spec_claims(&required_spec_claims);
+}
+
+fn is_hmac(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
 }
 
 mod crypto_keys_processor {
     use std::fs::read as read_bytes;
     use jsonwebtoken::{DecodingKey, EncodingKey};
+    use openssl::{
+        bn::BigNumContext,
+        ec::PointConversionForm,
+        pkey::{Id, PKey},
+    };
 
-    pub fn of_secret(secret_key: &String) -> (EncodingKey, DecodingKey) {
-        let secret_key_bytes = secret_key.as_bytes();
-        let encoding_key = EncodingKey::from_secret(&secret_key_bytes);
-        let decoding_key = DecodingKey::from_secret(&secret_key_bytes);
-        (encoding_key, decoding_key)
-    }
-
-    pub fn of_rsa_pem(
-        public_key_path: &String,
-        private_key_path: &String,
-    ) -> (EncodingKey, DecodingKey) {
-        let Ok(private_key) = read_bytes(&private_key_path) else {
-            panic!(r#"Panic: RSA-PME private key cannot be found from "{private_key_path}"."#);
-        };
+    pub fn encoding_key_of_rsa_pem(private_key_path: &str) -> EncodingKey {
+        let private_key = read_key(private_key_path, "RSA-PEM private key");
         let Ok(encoding_key) = EncodingKey::from_rsa_pem(&private_key) else {
             panic!("Panic: Invalid RSA-PEM private key.");
         };
+        encoding_key
+    }
+
+    pub fn encoding_key_of_rsa_der(private_key_path: &str) -> EncodingKey {
+        EncodingKey::from_rsa_der(&read_key(private_key_path, "RSA-DER private key"))
+    }
 
-        let Ok(public_key) = read_bytes(&public_key_path) else {
-            panic!(r#"Panic: RSA-PME public key cannot be found from "{public_key_path}"."#);
+    pub fn encoding_key_of_ec_pem(private_key_path: &str) -> EncodingKey {
+        let private_key = read_key(private_key_path, "EC-PEM private key");
+        let Ok(encoding_key) = EncodingKey::from_ec_pem(&private_key) else {
+            panic!("Panic: Invalid EC-PEM private key, PKCS#8 is expected.");
         };
+        encoding_key
+    }
+
+    pub fn encoding_key_of_ec_der(private_key_path: &str) -> EncodingKey {
+        EncodingKey::from_ec_der(&read_key(private_key_path, "EC-DER private key"))
+    }
+
+    pub fn encoding_key_of_ed_pem(private_key_path: &str) -> EncodingKey {
+        let private_key = read_key(private_key_path, "Ed-PEM private key");
+        let Ok(encoding_key) = EncodingKey::from_ed_pem(&private_key) else {
+            panic!("Panic: Invalid Ed-PEM private key, PKCS#8 is expected.");
+        };
+        encoding_key
+    }
+
+    pub fn encoding_key_of_ed_der(private_key_path: &str) -> EncodingKey {
+        EncodingKey::from_ed_der(&read_key(private_key_path, "Ed-DER private key"))
+    }
+
+    pub fn decoding_key_of_rsa_pem(public_key_path: &str) -> DecodingKey {
+        let public_key = read_key(public_key_path, "RSA-PEM public key");
         let Ok(decoding_key) = DecodingKey::from_rsa_pem(&public_key) else {
             panic!("Panic: Invalid RSA-PEM public key.");
         };
+        decoding_key
+    }
+
+    pub fn decoding_key_of_rsa_der(public_key_path: &str) -> DecodingKey {
+        DecodingKey::from_rsa_der(&read_key(public_key_path, "RSA-DER public key"))
+    }
+
+    pub fn decoding_key_of_ec_pem(public_key_path: &str) -> DecodingKey {
+        let public_key = read_key(public_key_path, "EC-PEM public key");
+        let Ok(decoding_key) = DecodingKey::from_ec_pem(&public_key) else {
+            panic!("Panic: Invalid EC-PEM public key.");
+        };
+        decoding_key
+    }
+
+    /**
+     * Verification takes the uncompressed point, extracted from the SubjectPublicKeyInfo.
+     **/
+    pub fn decoding_key_of_ec_der(public_key_path: &str) -> DecodingKey {
+        let public_key = read_key(public_key_path, "EC-DER public key");
+        let point = PKey::public_key_from_der(&public_key).ok()
+            .and_then(|public_key| public_key.ec_key().ok())
+            .and_then(|ec_key| {
+                let mut big_num_context = BigNumContext::new().ok()?;
+                ec_key.public_key()
+                    .to_bytes(ec_key.group(), PointConversionForm::UNCOMPRESSED, &mut big_num_context)
+                    .ok()
+            });
+        let Some(point) = point else {
+            panic!("Panic: Invalid EC-DER public key.");
+        };
+        DecodingKey::from_ec_der(&point)
+    }
 
-        (encoding_key, decoding_key)
+    pub fn decoding_key_of_ed_pem(public_key_path: &str) -> DecodingKey {
+        let public_key = read_key(public_key_path, "Ed-PEM public key");
+        let Ok(decoding_key) = DecodingKey::from_ed_pem(&public_key) else {
+            panic!("Panic: Invalid Ed-PEM public key.");
+        };
+        decoding_key
     }
 
-    pub fn of_rsa_der(
-        public_key_path: &String,
-        private_key_path: &String,
-    ) -> (EncodingKey, DecodingKey) {
-        let Ok(private_key) = read_bytes(&private_key_path) else {
-            panic!(r#"Panic: RSA-DER private key cannot be found from "{private_key_path}"."#);
+    /**
+     * Verification takes the raw key, extracted from the SubjectPublicKeyInfo.
+     **/
+    pub fn decoding_key_of_ed_der(public_key_path: &str) -> DecodingKey {
+        let public_key = read_key(public_key_path, "Ed-DER public key");
+        let raw_public_key = PKey::public_key_from_der(&public_key).ok()
+            .filter(|public_key| public_key.id() == Id::ED25519)
+            .and_then(|public_key| public_key.raw_public_key().ok());
+        let Some(raw_public_key) = raw_public_key else {
+            panic!("Panic: Invalid Ed-DER public key.");
         };
-        let encoding_key = EncodingKey::from_rsa_der(&private_key);
+        DecodingKey::from_ed_der(&raw_public_key)
+    }
+
+    fn read_key(path: &str, description: &str) -> Vec<u8> {
+        let Ok(key) = read_bytes(path) else {
+            panic!(r#"Panic: {description} cannot be found from "{path}"."#);
+        };
+        key
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::{Duration, Utc};
+    use jsonwebtoken::{jwk::AlgorithmParameters, Algorithm};
+    use openssl::{
+        ec::{EcGroup, EcKey},
+        nid::Nid,
+        pkey::{PKey, Private},
+    };
+
+    use super::{
+        metadata::{Key, Metadata, RetiredKey, VerificationKey},
+        JsonWebToken,
+    };
+
+    const TOKEN_ID: &str = "65f0a1b2c3d4e5f60718293a";
+    const ACCOUNT_ID: &str = "65f0a1b2c3d4e5f60718293b";
+
+    fn metadata(key_id: &str, secret: &str, retired_keys: Vec<RetiredKey>) -> Metadata {
+        metadata_of_key(key_id, None, Key::Secret(secret.to_string()), retired_keys)
+    }
+
+    fn metadata_of_key(
+        key_id: &str,
+        algorithm: Option<Algorithm>,
+        key: Key,
+        retired_keys: Vec<RetiredKey>,
+    ) -> Metadata {
+        Metadata {
+            issuer: None,
+            audience: None,
+            algorithm,
+            key_id: key_id.to_string(),
+            key,
+            retired_keys,
+            duration: 60 * 1000,
+            refresh_duration: 60 * 1000,
+        }
+    }
 
-        let Ok(public_key) = read_bytes(&public_key_path) else {
-            panic!(r#"Panic: RSA-DER private key cannot be found from "{public_key_path}"."#);
+    fn retired_key(key_id: &str, secret: &str, retirement: Option<i64>) -> RetiredKey {
+        RetiredKey {
+            id: key_id.to_string(),
+            algorithm: None,
+            key: VerificationKey::Secret(secret.to_string()),
+            retirement,
+        }
+    }
+
+    /**
+     * Write PKCS#8 private key and SubjectPublicKeyInfo public key files in PEM or DER,
+     * returning their paths in the order of (public, private).
+     **/
+    fn write_key_pair(name: &str, private_key: &PKey<Private>, is_pem: bool) -> (String, String) {
+        let directory = std::env::temp_dir();
+        let path = |part: &str| -> PathBuf {
+            directory.join(format!("cloudy-jwt-test-{}-{name}-{part}", std::process::id()))
+        };
+        let (public_key, private_key) = if is_pem {
+            (private_key.public_key_to_pem().unwrap(), private_key.private_key_to_pem_pkcs8().unwrap())
+        } else {
+            (private_key.public_key_to_der().unwrap(), private_key.private_key_to_pkcs8().unwrap())
         };
-        let decoding_key = DecodingKey::from_rsa_der(&public_key);
+        std::fs::write(path("pub"), public_key).unwrap();
+        std::fs::write(path("pri"), private_key).unwrap();
+        (path("pub").to_str().unwrap().to_string(), path("pri").to_str().unwrap().to_string())
+    }
+
+    fn ec_private_key(nid: Nid) -> PKey<Private> {
+        let group = EcGroup::from_curve_name(nid).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
 
-        (encoding_key, decoding_key)
+    fn encode(jsonwebtoken: &JsonWebToken) -> String {
+        let claims = jsonwebtoken.new_claims(TOKEN_ID, ACCOUNT_ID, &Utc::now(), None);
+        jsonwebtoken.encode_jwt(&claims).unwrap()
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_key_id_header() {
+        let jsonwebtoken = JsonWebToken::from_metadata(metadata("2024-07", "secret-2024-07", vec![]));
+        let jwt = encode(&jsonwebtoken);
+        let header = jsonwebtoken::decode_header(&jwt).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("2024-07"));
+        assert_eq!(jsonwebtoken.decode_jwt(&jwt).unwrap().id, TOKEN_ID);
+    }
+
+    #[test]
+    fn test_retired_key() {
+        let previous = JsonWebToken::from_metadata(metadata("2024-01", "secret-2024-01", vec![]));
+        let jwt = encode(&previous);
+
+        let retirement = (Utc::now() + Duration::days(1)).timestamp_millis();
+        let current = JsonWebToken::from_metadata(metadata(
+            "2024-07", "secret-2024-07", vec![retired_key("2024-01", "secret-2024-01", Some(retirement))],
+        ));
+        assert_eq!(current.decode_jwt(&jwt).unwrap().account, ACCOUNT_ID);
+
+        let retirement = (Utc::now() - Duration::days(1)).timestamp_millis();
+        let expired = JsonWebToken::from_metadata(metadata(
+            "2024-07", "secret-2024-07", vec![retired_key("2024-01", "secret-2024-01", Some(retirement))],
+        ));
+        assert!(expired.decode_jwt(&jwt).is_err());
+
+        let removed = JsonWebToken::from_metadata(metadata("2024-07", "secret-2024-07", vec![]));
+        assert!(removed.decode_jwt(&jwt).is_err());
+    }
+
+    #[test]
+    fn test_hmac_algorithms() {
+        let jsonwebtoken = JsonWebToken::from_metadata(metadata("2024-07", "secret-2024-07", vec![]));
+        assert!(jsonwebtoken.algorithms().is_empty());
+
+        let p256 = ec_private_key(Nid::X9_62_PRIME256V1);
+        let (public_key, private_key) = write_key_pair("ec-p256-hmac", &p256, true);
+        let retirement = (Utc::now() + Duration::days(1)).timestamp_millis();
+        let jsonwebtoken = JsonWebToken::from_metadata(metadata_of_key(
+            "ec", None, Key::EcPEM(public_key, private_key),
+            vec![retired_key("2024-07", "secret-2024-07", Some(retirement))],
+        ));
+        assert_eq!(jsonwebtoken.algorithms(), vec![Algorithm::ES256]);
+    }
+
+    #[test]
+    fn test_missing_key_id() {
+        let jsonwebtoken = JsonWebToken::from_metadata(metadata("default", "secret", vec![]));
+        let claims = jsonwebtoken.new_claims(TOKEN_ID, ACCOUNT_ID, &Utc::now(), None);
+        let jwt = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        ).unwrap();
+        assert_eq!(jsonwebtoken.decode_jwt(&jwt).unwrap().id, TOKEN_ID);
+    }
+
+    #[test]
+    fn test_ec_keys() {
+        let p256 = ec_private_key(Nid::X9_62_PRIME256V1);
+        let p384 = ec_private_key(Nid::SECP384R1);
+        let (public_key, private_key) = write_key_pair("ec-p256-pem", &p256, true);
+        let ec_pem = Key::EcPEM(public_key, private_key);
+        let (public_key, private_key) = write_key_pair("ec-p384-der", &p384, false);
+        let ec_der = Key::EcDER(public_key, private_key);
+
+        for (key, algorithm) in [(ec_pem, None), (ec_der, Some(Algorithm::ES384))] {
+            let jsonwebtoken = JsonWebToken::from_metadata(metadata_of_key("ec", algorithm, key, vec![]));
+            let jwt = encode(&jsonwebtoken);
+            assert_eq!(jsonwebtoken.decode_jwt(&jwt).unwrap().id, TOKEN_ID);
+
+            let jwk_set = jsonwebtoken.jwk_set();
+            assert!(matches!(jwk_set.find("ec").unwrap().algorithm, AlgorithmParameters::EllipticCurve(_)));
+        }
+    }
+
+    #[test]
+    fn test_ed_keys() {
+        let ed25519 = PKey::generate_ed25519().unwrap();
+        let (public_key, private_key) = write_key_pair("ed-pem", &ed25519, true);
+        let ed_pem = Key::EdPEM(public_key, private_key);
+        let (public_key, private_key) = write_key_pair("ed-der", &ed25519, false);
+        let ed_der = Key::EdDER(public_key, private_key);
+
+        for key in [ed_pem, ed_der] {
+            let jsonwebtoken = JsonWebToken::from_metadata(metadata_of_key("ed", None, key, vec![]));
+            let jwt = encode(&jsonwebtoken);
+            assert_eq!(jsonwebtoken::decode_header(&jwt).unwrap().alg, Algorithm::EdDSA);
+            assert_eq!(jsonwebtoken.decode_jwt(&jwt).unwrap().id, TOKEN_ID);
+            assert!(jsonwebtoken.algorithms().contains(&Algorithm::EdDSA));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_algorithm_of_other_family() {
+        let ed25519 = PKey::generate_ed25519().unwrap();
+        let (public_key, private_key) = write_key_pair("ed-rs256", &ed25519, true);
+        let key = Key::EdPEM(public_key, private_key);
+        JsonWebToken::from_metadata(metadata_of_key("ed", Some(Algorithm::RS256), key, vec![]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_algorithm_of_other_curve() {
+        let p256 = ec_private_key(Nid::X9_62_PRIME256V1);
+        let (public_key, private_key) = write_key_pair("ec-es384", &p256, true);
+        let key = Key::EcPEM(public_key, private_key);
+        JsonWebToken::from_metadata(metadata_of_key("ec", Some(Algorithm::ES384), key, vec![]));
+    }
+
+    fn metadata_of_environment(issuer: Option<&str>, audience: Option<&str>) -> Metadata {
+        let mut metadata = metadata("default", "shared-secret", vec![]);
+        metadata.issuer = issuer.map(str::to_string);
+        metadata.audience = audience.map(str::to_string);
+        metadata
+    }
+
+    #[test]
+    fn test_issuer_and_audience() {
+        let staging = JsonWebToken::from_metadata(
+            metadata_of_environment(Some("https://staging.example.com"), Some("cloudy")),
+        );
+        let production = JsonWebToken::from_metadata(
+            metadata_of_environment(Some("https://example.com"), Some("cloudy")),
+        );
+        let unconfigured = JsonWebToken::from_metadata(metadata_of_environment(None, None));
+
+        let staging_jwt = encode(&staging);
+        let claims = staging.decode_jwt(&staging_jwt).unwrap();
+        assert_eq!(claims.issuer.as_deref(), Some("https://staging.example.com"));
+        assert_eq!(claims.audience.as_deref(), Some("cloudy"));
+
+        // Cross-issuer tokens are rejected
+        assert!(production.decode_jwt(&staging_jwt).is_err());
+        assert!(staging.decode_jwt(&encode(&production)).is_err());
+        // Tokens without the configured issuer and audience are rejected
+        assert!(staging.decode_jwt(&encode(&unconfigured)).is_err());
+        // Tokens of an audience are rejected by an unconfigured audience
+        assert!(unconfigured.decode_jwt(&staging_jwt).is_err());
+    }
+
+    #[test]
+    fn test_other_audience() {
+        let drive = JsonWebToken::from_metadata(metadata_of_environment(Some("https://example.com"), Some("drive")));
+        let mail = JsonWebToken::from_metadata(metadata_of_environment(Some("https://example.com"), Some("mail")));
+        assert!(mail.decode_jwt(&encode(&drive)).is_err());
+    }
+
+    #[test]
+    fn test_not_before() {
+        let jsonwebtoken = JsonWebToken::from_metadata(metadata("default", "secret", vec![]));
+        let future = Utc::now() + Duration::hours(1);
+        let claims = jsonwebtoken.new_claims(TOKEN_ID, ACCOUNT_ID, &future, None);
+        let jwt = jsonwebtoken.encode_jwt(&claims).unwrap();
+        assert!(jsonwebtoken.decode_jwt(&jwt).is_err());
+    }
+
+}
@@ -0,0 +1,179 @@
+/**
+ * Public JWK parameters of the verifying keys, as per RFC 7517 and RFC 7518.
+ * Secrets of symmetric keys are never published.
+ **/
+use std::fs::read as read_bytes;
+
+use jsonwebtoken::jwk::{
+    AlgorithmParameters,
+    EllipticCurve,
+    EllipticCurveKeyParameters,
+    EllipticCurveKeyType,
+    OctetKeyPairParameters,
+    OctetKeyPairType,
+    RSAKeyParameters,
+    RSAKeyType,
+};
+use openssl::{
+    base64,
+    bn::{BigNum, BigNumContext},
+    nid::Nid,
+    pkey::{Id, PKey, Public},
+    rsa::Rsa,
+};
+
+pub fn of_rsa_pem(public_key_path: &str) -> AlgorithmParameters {
+    let Ok(public_key) = read_bytes(public_key_path) else {
+        panic!(r#"Panic: RSA-PEM public key cannot be found from "{public_key_path}"."#);
+    };
+    // Either SubjectPublicKeyInfo or PKCS#1
+    let Some(rsa) = PKey::public_key_from_pem(&public_key).ok()
+        .and_then(|public_key| public_key.rsa().ok())
+        .or_else(|| Rsa::public_key_from_pem_pkcs1(&public_key).ok()) else {
+        panic!("Panic: Invalid RSA-PEM public key.");
+    };
+    of_rsa(&rsa)
+}
+
+pub fn of_rsa_der(public_key_path: &str) -> AlgorithmParameters {
+    let Ok(public_key) = read_bytes(public_key_path) else {
+        panic!(r#"Panic: RSA-DER public key cannot be found from "{public_key_path}"."#);
+    };
+    // Either PKCS#1 or SubjectPublicKeyInfo
+    let Some(rsa) = Rsa::public_key_from_der_pkcs1(&public_key).ok()
+        .or_else(|| PKey::public_key_from_der(&public_key).ok()
+            .and_then(|public_key| public_key.rsa().ok())) else {
+        panic!("Panic: Invalid RSA-DER public key.");
+    };
+    of_rsa(&rsa)
+}
+
+pub fn of_ec_pem(public_key_path: &str) -> AlgorithmParameters {
+    let public_key = read_public_key(public_key_path, "EC-PEM", |key| PKey::public_key_from_pem(key).ok());
+    of_ec(&public_key)
+}
+
+pub fn of_ec_der(public_key_path: &str) -> AlgorithmParameters {
+    let public_key = read_public_key(public_key_path, "EC-DER", |key| PKey::public_key_from_der(key).ok());
+    of_ec(&public_key)
+}
+
+pub fn of_ed_pem(public_key_path: &str) -> AlgorithmParameters {
+    let public_key = read_public_key(public_key_path, "Ed-PEM", |key| PKey::public_key_from_pem(key).ok());
+    of_ed(&public_key)
+}
+
+pub fn of_ed_der(public_key_path: &str) -> AlgorithmParameters {
+    let public_key = read_public_key(public_key_path, "Ed-DER", |key| PKey::public_key_from_der(key).ok());
+    of_ed(&public_key)
+}
+
+fn read_public_key(
+    public_key_path: &str,
+    format: &str,
+    parse: impl Fn(&[u8]) -> Option<PKey<Public>>,
+) -> PKey<Public> {
+    let Ok(public_key) = read_bytes(public_key_path) else {
+        panic!(r#"Panic: {format} public key cannot be found from "{public_key_path}"."#);
+    };
+    let Some(public_key) = parse(&public_key) else {
+        panic!("Panic: Invalid {format} public key.");
+    };
+    public_key
+}
+
+fn of_rsa(rsa: &Rsa<Public>) -> AlgorithmParameters {
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: base64_url(&rsa.n().to_vec()),
+        e: base64_url(&rsa.e().to_vec()),
+    })
+}
+
+fn of_ec(public_key: &PKey<Public>) -> AlgorithmParameters {
+    let Ok(ec_key) = public_key.ec_key() else {
+        panic!("Panic: Invalid EC public key.");
+    };
+    let group = ec_key.group();
+    let curve = match group.curve_name() {
+        Some(Nid::X9_62_PRIME256V1) => EllipticCurve::P256,
+        Some(Nid::SECP384R1) => EllipticCurve::P384,
+        _ => panic!("Panic: EC public key of P-256 or P-384 is expected."),
+    };
+    let (Ok(mut x), Ok(mut y), Ok(mut big_num_context)) = (BigNum::new(), BigNum::new(), BigNumContext::new()) else {
+        panic!("Panic: Cannot allocate EC coordinates.");
+    };
+    if ec_key.public_key().affine_coordinates(group, &mut x, &mut y, &mut big_num_context).is_err() {
+        panic!("Panic: Invalid EC public key.");
+    }
+    // Coordinates are padded to the field size
+    let field_size = group.degree().div_ceil(8) as i32;
+    let (Ok(x), Ok(y)) = (x.to_vec_padded(field_size), y.to_vec_padded(field_size)) else {
+        panic!("Panic: Invalid EC public key.");
+    };
+    AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+        key_type: EllipticCurveKeyType::EC,
+        curve,
+        x: base64_url(&x),
+        y: base64_url(&y),
+    })
+}
+
+fn of_ed(public_key: &PKey<Public>) -> AlgorithmParameters {
+    if public_key.id() != Id::ED25519 {
+        panic!("Panic: Ed25519 public key is expected.");
+    }
+    let Ok(raw_public_key) = public_key.raw_public_key() else {
+        panic!("Panic: Invalid Ed25519 public key.");
+    };
+    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: base64_url(&raw_public_key),
+    })
+}
+
+/**
+ * Base64url encoding without padding of RFC 7515.
+ **/
+fn base64_url(bytes: &[u8]) -> String {
+    base64::encode_block(bytes)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+#[cfg(test)]
+mod test {
+    use jsonwebtoken::jwk::AlgorithmParameters;
+    use openssl::rsa::Rsa;
+
+    use super::{base64_url, of_rsa_der, of_rsa_pem};
+
+    #[test]
+    fn test_of_rsa() {
+        let rsa = Rsa::generate(2048).unwrap();
+        let directory = std::env::temp_dir();
+        let pem_path = directory.join(format!("cloudy-jwk-test-{}.pem", std::process::id()));
+        let der_path = directory.join(format!("cloudy-jwk-test-{}.der", std::process::id()));
+        std::fs::write(&pem_path, rsa.public_key_to_pem().unwrap()).unwrap();
+        std::fs::write(&der_path, rsa.public_key_to_der_pkcs1().unwrap()).unwrap();
+
+        for parameters in [of_rsa_pem(pem_path.to_str().unwrap()), of_rsa_der(der_path.to_str().unwrap())] {
+            let AlgorithmParameters::RSA(parameters) = parameters else {
+                panic!("Panic: RSA parameters are expected.");
+            };
+            assert_eq!(parameters.n, base64_url(&rsa.n().to_vec()));
+            assert_eq!(parameters.e, "AQAB");
+        }
+        let _ = std::fs::remove_file(pem_path);
+        let _ = std::fs::remove_file(der_path);
+    }
+
+    #[test]
+    fn test_base64_url() {
+        assert_eq!(base64_url(&[0x01, 0x00, 0x01]), "AQAB");
+        assert_eq!(base64_url(&[0xFB, 0xFF]), "-_8");
+    }
+
+}
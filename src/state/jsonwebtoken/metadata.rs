@@ -2,43 +2,163 @@
  * JWT metadata loading from [Config].
  * Detail config keys look at [key].
  **/
-
 use jsonwebtoken::Algorithm;
 
 use crate::state::Config;
 
 pub struct Metadata {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
     pub algorithm: Option<Algorithm>,
+    pub key_id: String,
     pub key: Key,
+    pub retired_keys: Vec<RetiredKey>,
     pub duration: i64,
+    pub refresh_duration: i64,
 }
 
+/**
+ * Signing key, paths of asymmetric keys are in the order of (public, private).
+ **/
 pub enum Key {
     Secret(String),
     RsaPEM(String, String),
     RsaDER(String, String),
+    EcPEM(String, String),
+    EcDER(String, String),
+    EdPEM(String, String),
+    EdDER(String, String),
+}
+
+/**
+ * Key no longer signing, still verifying the tokens it signed until [retirement].
+ **/
+pub struct RetiredKey {
+    pub id: String,
+    pub algorithm: Option<Algorithm>,
+    pub key: VerificationKey,
+    /**
+     * Timestamp in milliseconds, verifying until removed from [Config] if not specified.
+     **/
+    pub retirement: Option<i64>,
+}
+
+pub enum VerificationKey {
+    Secret(String),
+    RsaPEM(String),
+    RsaDER(String),
+    EcPEM(String),
+    EcDER(String),
+    EdPEM(String),
+    EdDER(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyFamily {
+    Hmac,
+    Rsa,
+    Ec,
+    Ed,
+}
+
+impl Key {
+    /**
+     * Secret or public part of the key.
+     **/
+    pub fn verification_key(&self) -> VerificationKey {
+        match self {
+            Self::Secret(secret) => VerificationKey::Secret(secret.clone()),
+            Self::RsaPEM(public_key, _) => VerificationKey::RsaPEM(public_key.clone()),
+            Self::RsaDER(public_key, _) => VerificationKey::RsaDER(public_key.clone()),
+            Self::EcPEM(public_key, _) => VerificationKey::EcPEM(public_key.clone()),
+            Self::EcDER(public_key, _) => VerificationKey::EcDER(public_key.clone()),
+            Self::EdPEM(public_key, _) => VerificationKey::EdPEM(public_key.clone()),
+            Self::EdDER(public_key, _) => VerificationKey::EdDER(public_key.clone()),
+        }
+    }
+}
+
+impl VerificationKey {
+    pub fn family(&self) -> KeyFamily {
+        match self {
+            Self::Secret(_) => KeyFamily::Hmac,
+            Self::RsaPEM(_) | Self::RsaDER(_) => KeyFamily::Rsa,
+            Self::EcPEM(_) | Self::EcDER(_) => KeyFamily::Ec,
+            Self::EdPEM(_) | Self::EdDER(_) => KeyFamily::Ed,
+        }
+    }
+}
+
+impl KeyFamily {
+
+    /**
+     * Algorithm used if none is configured.
+     **/
+    pub fn default_algorithm(&self) -> Algorithm {
+        match self {
+            Self::Hmac => Algorithm::HS256,
+            Self::Rsa => Algorithm::RS256,
+            Self::Ec => Algorithm::ES256,
+            Self::Ed => Algorithm::EdDSA,
+        }
+    }
+
+    pub fn supports(&self, algorithm: Algorithm) -> bool {
+        let family = match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Self::Hmac,
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 |
+            Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => Self::Rsa,
+            Algorithm::ES256 | Algorithm::ES384 => Self::Ec,
+            Algorithm::EdDSA => Self::Ed,
+        };
+        family == *self
+    }
+
 }
 
 impl Metadata {
     pub fn from_config(config: &Config) -> Self {
+        let issuer = config.issuer();
+        let audience = config.audience();
         let algorithm = config.algorithm();
+        let key_id = config.key_id();
         let key = config.key();
+        let retired_keys = config.retired_keys();
         let duration = config.duration();
+        let refresh_duration = config.refresh_duration();
 
-        Self { algorithm, key, duration }
+        Self { issuer, audience, algorithm, key_id, key, retired_keys, duration, refresh_duration }
     }
 }
 
-// Default duration is 7 days (in milliseconds).
-const DEFAULT_DURATION: i64 = 7 * 24 * 60 * 60 * 1000;
+// Default id of the signing key, stamped as "kid" into JWT headers.
+const DEFAULT_KEY_ID: &str = "default";
+
+// Default duration is 15 minutes (in milliseconds).
+const DEFAULT_DURATION: i64 = 15 * 60 * 1000;
+
+// Default refresh duration is 30 days (in milliseconds).
+const DEFAULT_REFRESH_DURATION: i64 = 30 * 24 * 60 * 60 * 1000;
 
 /**
  * JWT config keys in [Config].
  *
+ * JWT issuer is [issuer], the base URL of this service published in the discovery document.
+ * Where [issuer] = "jwt.issuer"
+ *
+ * JWT audience is [audience].
+ * Where [audience] = "jwt.audience"
+ *
+ * Both are embedded into and required from every token if specified,
+ * so that tokens cannot cross environments sharing a key.
+ *
  * JWT sign algorithm is [sign_algorithm]
  * Where [sign_algorithm] = "jwt.sign.alg"
  *
- * JWT signing with asymmetric key with RSA or symmetric key.
+ * JWT sign algorithm defaults to HS256, RS256, ES256 or EdDSA by the key family if not specified,
+ * and must match the key family otherwise.
+ *
+ * JWT signing with asymmetric key with RSA, EC, Ed25519 or symmetric key.
  *
  * Asymmetric key with RSA requires 2 keys, supporting both PEM and DER format.
  * PEM format requires both [key_rsa_pem_pri] and [key_rsa_pem_pub].
@@ -49,15 +169,54 @@ const DEFAULT_DURATION: i64 = 7 * 24 * 60 * 60 * 1000;
  * Where [key_rsa_der_pri] = "jwt.key.rsa-der"
  *       [key_rsa_der_pub] = "jwt.key.rsa-der"
  *
+ * Asymmetric key with EC (P-256 for ES256, P-384 for ES384) or Ed25519 requires 2 keys likewise,
+ * private keys in PKCS#8 and public keys in SubjectPublicKeyInfo.
+ * Where [key_ec_pem_pri] = "jwt.key.ec-pem.pri"
+ *       [key_ec_pem_pub] = "jwt.key.ec-pem.pub"
+ *       [key_ec_der_pri] = "jwt.key.ec-der.pri"
+ *       [key_ec_der_pub] = "jwt.key.ec-der.pub"
+ *       [key_ed_pem_pri] = "jwt.key.ed-pem.pri"
+ *       [key_ed_pem_pub] = "jwt.key.ed-pem.pub"
+ *       [key_ed_der_pri] = "jwt.key.ed-der.pri"
+ *       [key_ed_der_pub] = "jwt.key.ed-der.pub"
+ *
  * Symmetric key requires [key_secret].
  * Where [key_secret] = "jwt.key.secret"
  *
+ * JWT signing key id is [key_id], stamped as "kid" into JWT headers.
+ * Where [key_id] = "jwt.key.id": set as [super::DEFAULT_KEY_ID] if not specified
+ *
+ * Retired keys are listed in [retired], separated by commas, e.g. "2024-01,2024-07".
+ * Where [retired] = "jwt.retired"
+ * Each retired key <kid> verifies the tokens signed with its "kid" until [retired_until],
+ * with its public key or secret only, and its sign algorithm, defaulting to [sign_algorithm] if it matches the key family.
+ * Where [retired_sign_algorithm] = "jwt.retired.<kid>.alg"
+ *       [retired_key_secret] = "jwt.retired.<kid>.secret"
+ *       [retired_key_rsa_pem_pub] = "jwt.retired.<kid>.rsa-pem.pub"
+ *       [retired_key_rsa_der_pub] = "jwt.retired.<kid>.rsa-der.pub"
+ *       [retired_key_ec_pem_pub] = "jwt.retired.<kid>.ec-pem.pub"
+ *       [retired_key_ec_der_pub] = "jwt.retired.<kid>.ec-der.pub"
+ *       [retired_key_ed_pem_pub] = "jwt.retired.<kid>.ed-pem.pub"
+ *       [retired_key_ed_der_pub] = "jwt.retired.<kid>.ed-der.pub"
+ *       [retired_until] = "jwt.retired.<kid>.until": timestamp in millisecond, no retirement if not specified
+ *
  * JWT duration is [duration], in millisecond (= second * 1000).
- * Where [duration] = "jwt.duration": set as [super::DEFAULT_DURATION] (7 days) if not specified
+ * Where [duration] = "jwt.duration": set as [super::DEFAULT_DURATION] (15 minutes) if not specified
+ *
+ * Refresh token duration is [refresh_duration], in millisecond, renewed on every refresh.
+ * Where [refresh_duration] = "jwt.refresh.duration": set as [super::DEFAULT_REFRESH_DURATION] (30 days) if not specified
  **/
 mod key {
     use crate::str_vec;
 
+    pub fn issuer() -> Vec<String> {
+        str_vec!["jwt", "issuer"]
+    }
+
+    pub fn audience() -> Vec<String> {
+        str_vec!["jwt", "audience"]
+    }
+
     pub fn sign_algorithm() -> Vec<String> {
         str_vec!["jwt", "sign", "alg"]
     }
@@ -82,38 +241,124 @@ mod key {
         str_vec!["jwt", "key", "rsa-der"]
     }
 
+    pub fn key_ec_pem_pri() -> Vec<String> {
+        str_vec!["jwt", "key", "ec-pem", "pri"]
+    }
+
+    pub fn key_ec_pem_pub() -> Vec<String> {
+        str_vec!["jwt", "key", "ec-pem", "pub"]
+    }
+
+    pub fn key_ec_der_pri() -> Vec<String> {
+        str_vec!["jwt", "key", "ec-der", "pri"]
+    }
+
+    pub fn key_ec_der_pub() -> Vec<String> {
+        str_vec!["jwt", "key", "ec-der", "pub"]
+    }
+
+    pub fn key_ed_pem_pri() -> Vec<String> {
+        str_vec!["jwt", "key", "ed-pem", "pri"]
+    }
+
+    pub fn key_ed_pem_pub() -> Vec<String> {
+        str_vec!["jwt", "key", "ed-pem", "pub"]
+    }
+
+    pub fn key_ed_der_pri() -> Vec<String> {
+        str_vec!["jwt", "key", "ed-der", "pri"]
+    }
+
+    pub fn key_ed_der_pub() -> Vec<String> {
+        str_vec!["jwt", "key", "ed-der", "pub"]
+    }
+
+    pub fn key_id() -> Vec<String> {
+        str_vec!["jwt", "key", "id"]
+    }
+
+    pub fn retired() -> Vec<String> {
+        str_vec!["jwt", "retired"]
+    }
+
+    pub fn retired_sign_algorithm(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "alg"]
+    }
+
+    pub fn retired_key_secret(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "secret"]
+    }
+
+    pub fn retired_key_rsa_pem_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "rsa-pem", "pub"]
+    }
+
+    pub fn retired_key_rsa_der_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "rsa-der", "pub"]
+    }
+
+    pub fn retired_key_ec_pem_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "ec-pem", "pub"]
+    }
+
+    pub fn retired_key_ec_der_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "ec-der", "pub"]
+    }
+
+    pub fn retired_key_ed_pem_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "ed-pem", "pub"]
+    }
+
+    pub fn retired_key_ed_der_pub(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "ed-der", "pub"]
+    }
+
+    pub fn retired_until(key_id: &str) -> Vec<String> {
+        str_vec!["jwt", "retired", key_id, "until"]
+    }
+
     pub fn duration() -> Vec<String> {
         str_vec!["jwt", "duration"]
     }
 
+    pub fn refresh_duration() -> Vec<String> {
+        str_vec!["jwt", "refresh", "duration"]
+    }
+
 }
 
 trait MetadataConfig {
+    fn issuer(&self) -> Option<String>;
+    fn audience(&self) -> Option<String>;
     fn algorithm(&self) -> Option<Algorithm>;
+    fn key_id(&self) -> String;
     fn key(&self) -> Key;
+    fn retired_keys(&self) -> Vec<RetiredKey>;
     fn duration(&self) -> i64;
+    fn refresh_duration(&self) -> i64;
 }
 
 impl MetadataConfig for Config {
 
+    fn issuer(&self) -> Option<String> {
+        self.get(key::issuer())
+            .map(|issuer| issuer.trim_end_matches('/').to_string())
+    }
+
+    fn audience(&self) -> Option<String> {
+        self.get(key::audience())
+            .cloned()
+    }
+
     fn algorithm(&self) -> Option<Algorithm> {
         self.get(key::sign_algorithm())
-            .map(|algorithm| {
-                match algorithm.as_str() {
-                    "HS256" => Algorithm::HS256,
-                    "HS384" => Algorithm::HS384,
-                    "HS512" => Algorithm::HS512,
-                    "RS256" => Algorithm::RS256,
-                    "RS384" => Algorithm::RS384,
-                    "RS512" => Algorithm::RS512,
-                    "ES256" => Algorithm::ES256,
-                    "ES384" => Algorithm::ES384,
-                    "PS256" => Algorithm::PS256,
-                    "PS384" => Algorithm::PS384,
-                    "PS512" => Algorithm::PS512,
-                    _ => panic!(r#"Panic: Unknown JWT algorithm "{algorithm}"."#),
-                }
-            })
+            .map(|algorithm| parse_algorithm(algorithm))
+    }
+
+    fn key_id(&self) -> String {
+        self.get(key::key_id())
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_KEY_ID.to_string())
     }
 
     fn key(&self) -> Key {
@@ -129,17 +374,101 @@ impl MetadataConfig for Config {
             }
         }
 
+        if let Some(private_key) = self.get(key::key_ec_pem_pri()) {
+            if let Some(public_key) = self.get(key::key_ec_pem_pub()) {
+                return Key::EcPEM(public_key.to_string(), private_key.to_string())
+            }
+        }
+
+        if let Some(private_key) = self.get(key::key_ec_der_pri()) {
+            if let Some(public_key) = self.get(key::key_ec_der_pub()) {
+                return Key::EcDER(public_key.to_string(), private_key.to_string())
+            }
+        }
+
+        if let Some(private_key) = self.get(key::key_ed_pem_pri()) {
+            if let Some(public_key) = self.get(key::key_ed_pem_pub()) {
+                return Key::EdPEM(public_key.to_string(), private_key.to_string())
+            }
+        }
+
+        if let Some(private_key) = self.get(key::key_ed_der_pri()) {
+            if let Some(public_key) = self.get(key::key_ed_der_pub()) {
+                return Key::EdDER(public_key.to_string(), private_key.to_string())
+            }
+        }
+
         let Some(secret) = self.get(key::key_secret()) else {
             panic!("Panic: JWT signing/verifying key(s) is missing.");
         };
         Key::Secret(secret.clone())
     }
 
+    fn retired_keys(&self) -> Vec<RetiredKey> {
+        let Some(retired) = self.get(key::retired()) else {
+            return Vec::new();
+        };
+        retired.split(',')
+            .map(str::trim)
+            .filter(|key_id| !key_id.is_empty())
+            .map(|key_id| {
+                let algorithm = self.get(key::retired_sign_algorithm(key_id))
+                    .map(|algorithm| parse_algorithm(algorithm));
+                let key = if let Some(public_key) = self.get(key::retired_key_rsa_pem_pub(key_id)) {
+                    VerificationKey::RsaPEM(public_key.clone())
+                } else if let Some(public_key) = self.get(key::retired_key_rsa_der_pub(key_id)) {
+                    VerificationKey::RsaDER(public_key.clone())
+                } else if let Some(public_key) = self.get(key::retired_key_ec_pem_pub(key_id)) {
+                    VerificationKey::EcPEM(public_key.clone())
+                } else if let Some(public_key) = self.get(key::retired_key_ec_der_pub(key_id)) {
+                    VerificationKey::EcDER(public_key.clone())
+                } else if let Some(public_key) = self.get(key::retired_key_ed_pem_pub(key_id)) {
+                    VerificationKey::EdPEM(public_key.clone())
+                } else if let Some(public_key) = self.get(key::retired_key_ed_der_pub(key_id)) {
+                    VerificationKey::EdDER(public_key.clone())
+                } else if let Some(secret) = self.get(key::retired_key_secret(key_id)) {
+                    VerificationKey::Secret(secret.clone())
+                } else {
+                    panic!(r#"Panic: JWT verifying key of retired key "{key_id}" is missing."#);
+                };
+                let retirement = self.get(key::retired_until(key_id))
+                    .map(|until| {
+                        until.parse::<i64>()
+                            .unwrap_or_else(|_| panic!(r#"Panic: Invalid retirement "{until}" of JWT key "{key_id}"."#))
+                    });
+                RetiredKey { id: key_id.to_string(), algorithm, key, retirement }
+            })
+            .collect()
+    }
+
     fn duration(&self) -> i64 {
         self.get(key::duration())
-            .map(|duration| duration.parse::<i64>().ok())
-            .flatten()
-            .unwrap_or_else(|| DEFAULT_DURATION)
+            .and_then(|duration| duration.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_DURATION)
+    }
+
+    fn refresh_duration(&self) -> i64 {
+        self.get(key::refresh_duration())
+            .and_then(|duration| duration.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_REFRESH_DURATION)
     }
 
+}
+
+fn parse_algorithm(algorithm: &str) -> Algorithm {
+    match algorithm {
+        "HS256" => Algorithm::HS256,
+        "HS384" => Algorithm::HS384,
+        "HS512" => Algorithm::HS512,
+        "RS256" => Algorithm::RS256,
+        "RS384" => Algorithm::RS384,
+        "RS512" => Algorithm::RS512,
+        "ES256" => Algorithm::ES256,
+        "ES384" => Algorithm::ES384,
+        "PS256" => Algorithm::PS256,
+        "PS384" => Algorithm::PS384,
+        "PS512" => Algorithm::PS512,
+        "EdDSA" => Algorithm::EdDSA,
+        _ => panic!(r#"Panic: Unknown JWT algorithm "{algorithm}"."#),
+    }
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use std::{fmt, path::PathBuf};
+
+use lettre::{
+    address::AddressError,
+    message::{header::ContentType, Mailbox},
+    transport::smtp::{self, authentication::Credentials},
+    AsyncSmtpTransport,
+    AsyncTransport,
+    Message,
+    Tokio1Executor,
+};
+use mongodb::bson::oid::ObjectId;
+
+use super::Config;
+
+mod metadata;
+use metadata::{Metadata, Security};
+
+/**
+ * Outbound mail, sent from the configured sender through the configured transport.
+ * Clones share the same SMTP connection pool.
+ **/
+#[derive(Clone)]
+pub struct Mailer {
+    from: Mailbox,
+    transport: Transport,
+}
+
+#[derive(Clone)]
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(PathBuf),
+    Log,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Address(AddressError),
+    Message(lettre::error::Error),
+    Smtp(smtp::Error),
+    File(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(err) => write!(formatter, "Invalid address: {}", err),
+            Self::Message(err) => write!(formatter, "Invalid message: {}", err),
+            Self::Smtp(err) => write!(formatter, "SMTP error: {}", err),
+            Self::File(err) => write!(formatter, "File error: {}", err),
+        }
+    }
+}
+
+impl Mailer {
+
+    /**
+     * Mailer of the configured mail, none if "mail.transport" is not specified,
+     * in which case the routes sending mail respond 503 Service Unavailable.
+     **/
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let Some(metadata) = Metadata::from_config(config) else {
+            warn!("Mail is disabled, set \"mail.transport\" to send magic links and email verifications.");
+            return None;
+        };
+        Some(Self::from_metadata(metadata))
+    }
+
+    fn from_metadata(metadata: Metadata) -> Self {
+        let from = metadata.from.parse::<Mailbox>()
+            .unwrap_or_else(|err| panic!("Panic: Invalid mail sender {:?} ({}).", metadata.from, err));
+
+        let transport = match metadata.transport {
+            metadata::Transport::Smtp { host, port, security, credential } => {
+                let builder = match security {
+                    Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+                    Security::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+                    Security::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host)),
+                };
+                let mut builder = builder
+                    .unwrap_or_else(|err| panic!("Panic: Invalid SMTP host {:?} ({}).", host, err));
+                if let Some(port) = port {
+                    builder = builder.port(port);
+                }
+                if let Some((username, password)) = credential {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+                Transport::Smtp(builder.build())
+            }
+            metadata::Transport::File(directory) => Transport::File(PathBuf::from(directory)),
+            metadata::Transport::Log => Transport::Log,
+        };
+
+        Self { from, transport }
+    }
+
+    /**
+     * Send a plain text message to the address [to].
+     **/
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), Error> {
+        let to = to.parse::<Mailbox>()
+            .map_err(Error::Address)?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(Error::Message)?;
+
+        match &self.transport {
+            Transport::Smtp(transport) => {
+                transport.send(message).await
+                    .map_err(Error::Smtp)?;
+            }
+            Transport::File(directory) => {
+                tokio::fs::create_dir_all(directory).await
+                    .map_err(Error::File)?;
+                let path = directory.join(format!("{}.eml", ObjectId::new().to_hex()));
+                tokio::fs::write(path, message.formatted()).await
+                    .map_err(Error::File)?;
+            }
+            Transport::Log => {
+                info!("Mail:\n{}", String::from_utf8_lossy(&message.formatted()));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * [send] off the request path, where failures are logged only.
+     **/
+    pub fn send_in_background(&self, to: String, subject: &'static str, body: String) {
+        let mailer = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = mailer.send(&to, subject, body).await {
+                warn!("Failed to send the mail {:?} ({}).", subject, err);
+            }
+        });
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+
+    fn file_mailer(directory: &Path) -> Mailer {
+        Mailer::from_metadata(Metadata {
+            from: "Cloudy <noreply@example.com>".into(),
+            transport: metadata::Transport::File(directory.to_string_lossy().into_owned()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_file_transport() {
+        let directory = std::env::temp_dir().join(format!("cloudy-mail-{}", ObjectId::new().to_hex()));
+        let mailer = file_mailer(&directory);
+        mailer.send("user@example.com", "Subject", "Body".into()).await.unwrap();
+
+        let entries = std::fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        let message = std::fs::read_to_string(&entries[0]).unwrap();
+        assert!(message.contains("To: user@example.com"));
+        assert!(message.contains("Subject: Subject"));
+        assert!(message.contains("Body"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_address() {
+        let directory = std::env::temp_dir().join(format!("cloudy-mail-{}", ObjectId::new().to_hex()));
+        let mailer = file_mailer(&directory);
+        let result = mailer.send("not an address", "Subject", "Body".into()).await;
+        assert!(matches!(result, Err(Error::Address(_))));
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(Mailer::from_config(&Config::of_pairs(&[])).is_none());
+        assert!(Mailer::from_config(&Config::of_pairs(&[("mail.from", "Cloudy <noreply@example.com>")])).is_none());
+        assert!(Mailer::from_config(&Config::of_pairs(&[("mail.transport", "log")])).is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_sender() {
+        Mailer::from_metadata(Metadata {
+            from: "not an address".into(),
+            transport: metadata::Transport::Log,
+        });
+    }
+}
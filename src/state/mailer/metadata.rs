@@ -0,0 +1,162 @@
+/**
+ * Mailer metadata loading from [Config].
+ * Detail config keys look at [key].
+ **/
+use crate::state::Config;
+
+pub struct Metadata {
+    pub from: String,
+    pub transport: Transport,
+}
+
+pub enum Transport {
+    Smtp {
+        host: String,
+        port: Option<u16>,
+        security: Security,
+        credential: Option<(String, String)>,
+    },
+    /**
+     * Write every message as an .eml file into the directory.
+     **/
+    File(String),
+    /**
+     * Log every message in full, links and tokens included, for development only.
+     **/
+    Log,
+}
+
+pub enum Security {
+    /**
+     * Implicit TLS, port 465 by default.
+     **/
+    Tls,
+    /**
+     * STARTTLS upgrade, port 587 by default.
+     **/
+    StartTls,
+    /**
+     * Plain text, for local relays only.
+     **/
+    None,
+}
+
+impl Metadata {
+    /**
+     * Metadata of the configured mail, none if "mail.transport" is not specified.
+     **/
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let transport = config.transport()?;
+        let from = config.from();
+
+        Some(Self { from, transport })
+    }
+}
+
+const DEFAULT_FROM: &str = "Cloudy <noreply@localhost>";
+
+const DEFAULT_FILE_DIRECTORY: &str = "mail";
+
+/**
+ * Mail config keys in [Config].
+ *
+ * Sender of every message is [from], in the form of "Name <address>" or "address".
+ * Where [from] = "mail.from", set as [DEFAULT_FROM] if not specified.
+ *
+ * Transport is [transport], one of "smtp", "file" or "log", never set by default
+ * since messages carry login and verification tokens, which the "log" transport writes into the log.
+ * Mail is disabled if not specified, see [super::Mailer::from_config].
+ * Where [transport] = "mail.transport"
+ *
+ * SMTP transport requires [smtp_host], with the optional [smtp_port], [smtp_security]
+ * of "tls", "starttls" or "none" (set as "starttls" if not specified),
+ * and both [smtp_username] and [smtp_password] for authentication.
+ * Where [smtp_host] = "mail.smtp.host"
+ *       [smtp_port] = "mail.smtp.port"
+ *       [smtp_security] = "mail.smtp.security"
+ *       [smtp_username] = "mail.smtp.username"
+ *       [smtp_password] = "mail.smtp.password"
+ *
+ * File transport writes into [file_directory], set as [DEFAULT_FILE_DIRECTORY] if not specified.
+ * Where [file_directory] = "mail.file.dir"
+ **/
+mod key {
+    use crate::str_vec;
+
+    pub fn from() -> Vec<String> {
+        str_vec!["mail", "from"]
+    }
+
+    pub fn transport() -> Vec<String> {
+        str_vec!["mail", "transport"]
+    }
+
+    pub fn smtp_host() -> Vec<String> {
+        str_vec!["mail", "smtp", "host"]
+    }
+
+    pub fn smtp_port() -> Vec<String> {
+        str_vec!["mail", "smtp", "port"]
+    }
+
+    pub fn smtp_security() -> Vec<String> {
+        str_vec!["mail", "smtp", "security"]
+    }
+
+    pub fn smtp_username() -> Vec<String> {
+        str_vec!["mail", "smtp", "username"]
+    }
+
+    pub fn smtp_password() -> Vec<String> {
+        str_vec!["mail", "smtp", "password"]
+    }
+
+    pub fn file_directory() -> Vec<String> {
+        str_vec!["mail", "file", "dir"]
+    }
+}
+
+trait MetadataConfig {
+    fn from(&self) -> String;
+    fn transport(&self) -> Option<Transport>;
+}
+
+impl MetadataConfig for Config {
+    fn from(&self) -> String {
+        self.get(key::from())
+            .cloned()
+            .unwrap_or(DEFAULT_FROM.into())
+    }
+
+    fn transport(&self) -> Option<Transport> {
+        let transport = match self.get(key::transport())?.as_str() {
+            "smtp" => {
+                let Some(host) = self.get(key::smtp_host()).cloned() else {
+                    panic!("Panic: SMTP host is required for SMTP mail transport.");
+                };
+                let port = self.get(key::smtp_port())
+                    .map(|port| port.parse::<u16>().unwrap_or_else(|_| {
+                        panic!("Panic: Invalid SMTP port {:?}.", port)
+                    }));
+                let security = match self.get(key::smtp_security()).map(String::as_str) {
+                    Some("tls") => Security::Tls,
+                    Some("starttls") | None => Security::StartTls,
+                    Some("none") => Security::None,
+                    Some(security) => panic!("Panic: Unknown SMTP security {:?}.", security),
+                };
+                let credential = self.get(key::smtp_username()).cloned()
+                    .zip(self.get(key::smtp_password()).cloned());
+                Transport::Smtp { host, port, security, credential }
+            }
+            "file" => {
+                let directory = self.get(key::file_directory())
+                    .cloned()
+                    .unwrap_or(DEFAULT_FILE_DIRECTORY.into());
+                Transport::File(directory)
+            }
+            "log" => Transport::Log,
+            transport => panic!("Panic: Unknown mail transport {:?}.", transport),
+        };
+        Some(transport)
+    }
+}
@@ -0,0 +1,44 @@
+use openssl::{base64, error::ErrorStack, rand::rand_bytes, sha::sha256};
+
+/**
+ * Random bytes of the generated tokens, tickets and nonces, 64 hex characters.
+ **/
+pub const BYTES: usize = 32;
+
+/**
+ * Random secret of [bytes] random bytes, in lowercase hex.
+ **/
+pub fn generate(bytes: usize) -> Result<String, ErrorStack> {
+    let mut secret_bytes = vec![0; bytes];
+    rand_bytes(&mut secret_bytes)?;
+    Ok(secret_bytes.iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/**
+ * Hash of a presented secret, in base64 of SHA-256, as stored in place of the secret.
+ **/
+pub fn hash(secret: &str) -> String {
+    base64::encode_block(&sha256(secret.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let secret = generate(BYTES).unwrap();
+        assert_eq!(secret.len(), BYTES * 2);
+        assert!(secret.chars().all(|char| matches!(char, '0'..='9' | 'a'..='f')));
+        assert_ne!(secret, generate(BYTES).unwrap());
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash("secret"), hash(" secret\n"));
+        assert_ne!(hash("secret"), hash("Secret"));
+        assert_eq!(hash("secret").len(), 44);
+    }
+}
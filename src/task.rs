@@ -0,0 +1,13 @@
+use rocket::{Build, Rocket};
+
+mod account_purge;
+
+pub trait Task {
+    fn attach_tasks(self) -> Self;
+}
+
+impl Task for Rocket<Build> {
+    fn attach_tasks(self) -> Self {
+        self.attach(account_purge::fairing())
+    }
+}
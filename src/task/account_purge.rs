@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    error::Result,
+};
+use rocket::fairing::AdHoc;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::{
+    state::{
+        database::{collection::Account, Collections},
+        Config,
+        Database,
+    },
+    str_vec,
+};
+
+/**
+ * Purge the accounts whose deletion grace period has elapsed, periodically from the liftoff
+ * until the shutdown of Rocket.
+ **/
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Account Purge", |rocket| Box::pin(async move {
+        let period = rocket.state::<Config>().unwrap().purge_interval_millis();
+        let collections = rocket.state::<Database>().unwrap().collections.clone();
+        let mut shutdown = rocket.shutdown();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(period));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if let Err(err) = purge(&collections).await {
+                            warn!("Failed to purge the deleted accounts ({}).", err);
+                        }
+                    }
+                    _ = &mut shutdown => break,
+                }
+            }
+        });
+    }))
+}
+
+/**
+ * Remove every account due for purge, along with its owned data.
+ * The owned data is removed first, so that a failure leaves the account to be purged again,
+ * and the account is removed only if its deletion has not been cancelled in the meantime.
+ **/
+async fn purge(collections: &Collections) -> Result<()> {
+    loop {
+        let Some(account) = collections.account.find_one(due_filter(None)).await? else {
+            return Ok(());
+        };
+        purge_owned(collections, &account).await?;
+        collections.account.delete_one(due_filter(Some(account.id))).await?;
+    }
+}
+
+/**
+ * Filter matching the accounts due for purge, or only the account of [id].
+ **/
+fn due_filter(id: Option<ObjectId>) -> Document {
+    let mut filter = doc! {
+        "deletion": { "$lte": Utc::now().timestamp_millis() },
+    };
+    if let Some(id) = id {
+        filter.insert("_id", id);
+    }
+    filter
+}
+
+async fn purge_owned(collections: &Collections, account: &Account) -> Result<()> {
+    let filter = doc! { "account": account.id };
+    collections.token.delete_many(filter.clone()).await?;
+    collections.access_token.delete_many(filter.clone()).await?;
+    collections.mfa_ticket.delete_many(filter.clone()).await?;
+    collections.mail_token.delete_many(filter).await?;
+
+    let filter = doc! { "username": &account.username };
+    collections.challenge.delete_many(filter).await?;
+
+    let filter = doc! {
+        "$or": [
+            { "issuer": account.id },
+            { "redeemer": account.id },
+        ],
+    };
+    collections.invitation.delete_many(filter).await?;
+    Ok(())
+}
+
+// 1 hour
+const PURGE_INTERVAL: u64 = 60 * 60 * 1000;
+
+trait AccountPurge {
+    fn purge_interval_millis(&self) -> u64;
+}
+
+impl AccountPurge for Config {
+    /**
+     * Interval in milliseconds between the purges of deleted accounts: "account.deletion.purge-interval",
+     * set as [PURGE_INTERVAL] if not specified.
+     **/
+    fn purge_interval_millis(&self) -> u64 {
+        self.get(str_vec!["account", "deletion", "purge-interval"])
+            .and_then(|purge_interval| purge_interval.parse::<u64>().ok())
+            .filter(|purge_interval| *purge_interval > 0)
+            .unwrap_or(PURGE_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_due_filter() {
+        let filter = due_filter(None);
+        let deletion = filter.get_document("deletion").unwrap().get_i64("$lte").unwrap();
+        assert!((Utc::now().timestamp_millis() - deletion).abs() < 1000);
+        assert!(!filter.contains_key("_id"));
+
+        let id = ObjectId::new();
+        let filter = due_filter(Some(id));
+        assert_eq!(filter.get_object_id("_id").unwrap(), id);
+        assert!(filter.contains_key("deletion"));
+    }
+
+    #[test]
+    fn test_purge_interval() {
+        assert_eq!(Config::of_pairs(&[]).purge_interval_millis(), PURGE_INTERVAL);
+        let config = Config::of_pairs(&[("account.deletion.purge-interval", "60000")]);
+        assert_eq!(config.purge_interval_millis(), 60000);
+        let config = Config::of_pairs(&[("account.deletion.purge-interval", "0")]);
+        assert_eq!(config.purge_interval_millis(), PURGE_INTERVAL);
+    }
+}